@@ -1,10 +1,16 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, process::ExitCode};
 
 use clap::Parser;
 
 use crate::{
     command::settings::CommandManager,
+    common::IO,
+    env::mutators::EnvMutator,
+    flows::{cli::FlowCommand, settings::FlowsManager},
+    generators::mutators::GeneratorMutator,
+    prompt::settings::PromptManager,
     run::{cli::RunCommand, settings::RunManager},
+    secrets::mutators::SecretMutator,
     settings::{file::FileStorage, global_settings::GlobalSettings},
     variables::builder::VariablesBuilder,
     workspaces::{cli::WorkspacesCommand, settings::WorkspacesManager},
@@ -14,6 +20,10 @@ use crate::{
 pub struct Args {
     #[command(subcommand)]
     command: Commands,
+
+    /// Never prompt, and fail with the values that are missing instead
+    #[arg(long, global = true, default_value = "false")]
+    no_input: bool,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -23,34 +33,82 @@ pub enum Commands {
 
     #[command(subcommand)]
     Workspaces(WorkspacesCommand),
+
+    #[command(subcommand)]
+    Flow(FlowCommand),
 }
 
-pub fn run() {
+pub fn run() -> ExitCode {
     let input = Args::parse();
+    if input.no_input {
+        IO::disable_input();
+    }
+
     let global_settings = RefCell::new(GlobalSettings::new(FileStorage::new(None)));
     let mut variable_builder = VariablesBuilder::new();
 
-    let mut command_settings = CommandManager::new(&global_settings);
+    let _command_settings = CommandManager::new(&global_settings);
 
     let mut workspace_settings = WorkspacesManager::new(&global_settings);
     let workspace_mutator = workspace_settings.get_workspace_mutator();
 
+    variable_builder.add_extractor(&workspace_mutator);
     variable_builder.add_inserter(&workspace_mutator);
 
+    // Workspace values are offered as completions at prompts
+    let completions = workspace_settings
+        .get_current_workspace()
+        .variables
+        .values()
+        .cloned()
+        .collect();
+    let mut prompt_settings = PromptManager::new(&global_settings, completions);
+
+    let mut flow_settings = FlowsManager::new(&global_settings);
+
     let mut run_settings = RunManager::new(&global_settings);
     let run_mutators = run_settings.get_mutators();
 
     variable_builder.add_extractor(&run_mutators);
     variable_builder.add_inserter(&run_mutators);
 
-    match &input.command {
-        Commands::Run(variants) => {
-            RunCommand::run_match(variants, &mut run_settings, &mut variable_builder)
-        }
+    let generator_mutator = GeneratorMutator {};
+
+    variable_builder.add_extractor(&generator_mutator);
+    variable_builder.add_inserter(&generator_mutator);
+
+    let secret_mutator = SecretMutator::new();
+
+    variable_builder.add_extractor(&secret_mutator);
+    variable_builder.add_inserter(&secret_mutator);
+
+    let env_mutator = EnvMutator {};
+
+    variable_builder.add_extractor(&env_mutator);
+    variable_builder.add_inserter(&env_mutator);
+
+    let exit_code = match &input.command {
+        Commands::Run(variants) => RunCommand::run_match(
+            variants,
+            &mut run_settings,
+            &mut variable_builder,
+            &mut workspace_settings,
+            &mut prompt_settings,
+        ),
+        Commands::Flow(variants) => FlowCommand::cli_match(
+            variants,
+            &mut flow_settings,
+            &mut run_settings,
+            &mut variable_builder,
+            &mut workspace_settings,
+            &mut prompt_settings,
+        ),
         Commands::Workspaces(variants) => {
             WorkspacesCommand::cli_match(variants, &mut workspace_settings)
         }
-    }
+    };
 
+    workspace_settings.save_current_variables();
     global_settings.borrow_mut().write();
+    exit_code
 }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[cfg(test)]
 use mockall::predicate::*;
@@ -9,6 +10,9 @@ use serde::{Deserialize, Serialize};
 
 pub struct IO {}
 
+// Set by `--no-input`, or once stdin has been read for variables
+static INPUT_DISABLED: AtomicBool = AtomicBool::new(false);
+
 #[cfg_attr(test, automock)]
 impl IO {
     pub fn prompt(message: &str) -> String {
@@ -22,9 +26,198 @@ impl IO {
         output.trim().to_string()
     }
 
+    /// Reads a line that can be edited, starting out as `initial`. Up and down go through
+    /// `history`, Ctrl-R searches it and tab completes from `completions`.
+    /// Piped input is read as it is.
+    pub fn read_line(
+        message: &str,
+        initial: &str,
+        history: &[String],
+        completions: &[String],
+    ) -> String {
+        use crate::prompt::editor::{LineEditor, Outcome};
+        use std::io::Write;
+        use termion::{clear, cursor, input::TermRead, raw::IntoRawMode};
+
+        if !termion::is_tty(&std::io::stdin()) {
+            return IO::prompt(message);
+        }
+
+        let tty = termion::get_tty().expect("unable to open the terminal");
+        let keys = tty.try_clone().expect("unable to open the terminal").keys();
+        let mut tty = tty
+            .into_raw_mode()
+            .expect("unable to switch the terminal to raw mode");
+
+        let draw = |tty: &mut dyn Write, editor: &LineEditor| {
+            match editor.query() {
+                Some(query) => {
+                    let found = editor.found().map_or("", String::as_str);
+                    write!(
+                        tty,
+                        "\r{}(search) '{}': {}",
+                        clear::AfterCursor,
+                        query,
+                        found
+                    )?;
+                }
+                None => {
+                    let line = editor.line();
+                    write!(tty, "\r{}{}{}", clear::AfterCursor, message, line)?;
+                    let behind = line.chars().count() - editor.cursor();
+                    if behind > 0 {
+                        write!(tty, "{}", cursor::Left(behind as u16))?;
+                    }
+                }
+            }
+            tty.flush()
+        };
+
+        let mut editor = LineEditor::new(initial, history, completions);
+        draw(&mut tty, &editor).expect("unable to draw the prompt");
+        for key in keys {
+            match editor.handle(key.expect("unable to read from the terminal")) {
+                Outcome::Editing => draw(&mut tty, &editor).expect("unable to draw the prompt"),
+                Outcome::Done(line) => {
+                    write!(tty, "\r{}{}{}\r\n", clear::AfterCursor, message, line)
+                        .and_then(|_| tty.flush())
+                        .expect("unable to draw the prompt");
+                    return line.trim().to_string();
+                }
+                Outcome::Cancelled => {
+                    write!(tty, "\r\n").ok();
+                    drop(tty);
+                    std::process::exit(130);
+                }
+            }
+        }
+
+        // The terminal closed before the line was finished
+        editor.line().trim().to_string()
+    }
+
+    /// Reads a value without showing it as it is typed
+    pub fn prompt_secret(message: &str) -> String {
+        use std::io::{stdin, Write};
+        use termion::input::TermRead;
+
+        if !termion::is_tty(&stdin()) {
+            let mut output = String::new();
+            stdin().read_line(&mut output).expect("No input");
+            return output.trim_end_matches(['\r', '\n']).to_string();
+        }
+
+        let mut tty = termion::get_tty().expect("unable to open the terminal");
+        write!(tty, "{}", message)
+            .and_then(|_| tty.flush())
+            .expect("unable to write to the terminal");
+
+        let secret = tty
+            .try_clone()
+            .and_then(|mut input| input.read_passwd(&mut tty))
+            .expect("unable to read from the terminal")
+            .unwrap_or_default();
+        // The new line typed at the end isn't echoed either
+        writeln!(tty).expect("unable to write to the terminal");
+
+        secret
+    }
+
+    /// Lets the user pick one of the options with the arrow keys, starting on `selected`.
+    /// The picker is drawn on the terminal itself so piped output isn't touched.
+    /// Fails when there is nothing to pick from or no terminal to pick on.
+    pub fn select(message: &str, options: &[String], selected: usize) -> Result<String, String> {
+        use std::io::Write;
+        use termion::{clear, cursor, event::Key, input::TermRead, raw::IntoRawMode, style};
+
+        if options.is_empty() {
+            return Err(format!("{}: there are no values to choose from", message));
+        }
+
+        let no_terminal = |_| format!("{}: a value can only be chosen from a terminal", message);
+        let tty = termion::get_tty().map_err(no_terminal)?;
+        let keys = tty.try_clone().map_err(no_terminal)?.keys();
+        let mut tty = tty.into_raw_mode().map_err(no_terminal)?;
+
+        let mut selected = selected.min(options.len() - 1);
+        let draw = |tty: &mut dyn Write, selected: usize| {
+            // Raw mode doesn't return to the start of the line on a new line
+            write!(tty, "\r{}{} (↑/↓, Enter)\r\n", clear::AfterCursor, message)?;
+            for (index, option) in options.iter().enumerate() {
+                match index == selected {
+                    true => write!(tty, "{}> {}{}\r\n", style::Invert, option, style::Reset)?,
+                    false => write!(tty, "  {}\r\n", option)?,
+                }
+            }
+            write!(tty, "{}", cursor::Up(options.len() as u16 + 1))?;
+            tty.flush()
+        };
+
+        draw(&mut tty, selected).expect("unable to draw the picker");
+        for key in keys {
+            match key.expect("unable to read from the terminal") {
+                Key::Up | Key::Char('k') => selected = selected.saturating_sub(1),
+                Key::Down | Key::Char('j') => selected = (selected + 1).min(options.len() - 1),
+                Key::Char('\n') => break,
+                Key::Ctrl('c') | Key::Esc => {
+                    write!(tty, "\r{}", clear::AfterCursor).ok();
+                    drop(tty);
+                    std::process::exit(130);
+                }
+                _ => continue,
+            }
+            draw(&mut tty, selected).expect("unable to draw the picker");
+        }
+
+        let chosen = options[selected].to_owned();
+        write!(tty, "\r{}{}: {}\r\n", clear::AfterCursor, message, chosen)
+            .and_then(|_| tty.flush())
+            .expect("unable to draw the picker");
+
+        Ok(chosen)
+    }
+
     pub fn output(message: &str) {
         println!("{}", message);
     }
+
+    pub fn error(message: &str) {
+        eprintln!("{}", message);
+    }
+
+    // Extra details go to stderr, and only when someone is watching, so they never end up in piped output
+    pub fn info(message: &str) {
+        if termion::is_tty(&std::io::stderr()) {
+            eprintln!("{}", message);
+        }
+    }
+
+    pub fn output_bytes(bytes: &[u8]) {
+        use std::io::{stdout, Write};
+        let mut stdout = stdout();
+        stdout
+            .write_all(bytes)
+            .and_then(|_| stdout.flush())
+            .expect("unable to write to stdout");
+    }
+
+    pub fn is_terminal() -> bool {
+        termion::is_tty(&std::io::stdout())
+    }
+
+    // Whether someone can answer prompts, rather than input being piped in
+    pub fn is_interactive() -> bool {
+        IO::accepts_input() && termion::is_tty(&std::io::stdin())
+    }
+
+    /// Whether anything may be asked for at all, typed or piped in
+    pub fn accepts_input() -> bool {
+        !INPUT_DISABLED.load(Ordering::Relaxed)
+    }
+
+    pub fn disable_input() {
+        INPUT_DISABLED.store(true, Ordering::Relaxed);
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Default)]
@@ -0,0 +1 @@
+pub mod mutators;
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use crate::variables::{
+    lexer::{tokenize, TemplateError, Token},
+    placeholder::Placeholder,
+    Extractor, Inserter,
+};
+
+static OPENING: &str = "e{";
+static CLOSING: &str = "}";
+
+/// Fills `e{NAME}` from the process environment
+pub struct EnvMutator {}
+
+impl Inserter for EnvMutator {
+    fn opening(&self) -> &str {
+        OPENING
+    }
+
+    fn value(
+        &self,
+        placeholder: &Placeholder,
+        _value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        std::env::var(&placeholder.name).ok()
+    }
+
+    // Values are often credentials, so only the placeholder is ever shown
+    fn display(
+        &self,
+        _placeholder: &Placeholder,
+        _value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        None
+    }
+}
+
+impl Extractor for EnvMutator {
+    // Returns the variables that aren't set and have no default, and checks the filters
+    // work on the ones that are
+    fn extract(&self, template: &str) -> Result<Vec<Placeholder>, TemplateError> {
+        let mut missing = Vec::new();
+        for token in tokenize(template, OPENING, CLOSING)? {
+            if let Token::Placeholder {
+                placeholder,
+                column,
+                ..
+            } = token
+            {
+                let value = match (std::env::var(&placeholder.name), &placeholder.default) {
+                    (Ok(value), _) => value,
+                    (Err(_), Some(default)) => default.to_owned(),
+                    (Err(_), None) => {
+                        missing.push(placeholder);
+                        continue;
+                    }
+                };
+
+                placeholder
+                    .apply_filters(&value)
+                    .map_err(|message| TemplateError::at_column(template, column, message))?;
+            }
+        }
+
+        Ok(missing)
+    }
+
+    fn missing_from(&self) -> Option<String> {
+        Some("environment".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserter_should_read_environment() {
+        std::env::set_var("CURLD_TEST_TOKEN", "abc");
+
+        let mutator = EnvMutator {};
+
+        assert_eq!(
+            mutator.value(&Placeholder::new("CURLD_TEST_TOKEN"), &HashMap::new()),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            mutator.value(&Placeholder::new("CURLD_TEST_UNSET"), &HashMap::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn display_should_never_show_values() {
+        std::env::set_var("CURLD_TEST_DISPLAY_TOKEN", "supersecret");
+
+        let mutator = EnvMutator {};
+
+        assert_eq!(
+            mutator.display(
+                &Placeholder::new("CURLD_TEST_DISPLAY_TOKEN"),
+                &HashMap::new()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn extractor_should_return_unset_variables() {
+        std::env::set_var("CURLD_TEST_USER", "ada");
+
+        let mutator = EnvMutator {};
+
+        assert_eq!(
+            mutator.extract("e{CURLD_TEST_USER} e{CURLD_TEST_PORT:80}"),
+            Ok(vec![])
+        );
+        assert_eq!(
+            mutator.extract("user=e{CURLD_TEST_USER}&key=e{CURLD_TEST_MISSING}"),
+            Ok(vec![Placeholder::new("CURLD_TEST_MISSING")])
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+use crate::common::{CurldCommand, IO};
+use crate::prompt::settings::PromptManager;
+use crate::run::cli::{OutputInput, RunCommand, VarsInput};
+use crate::run::history::HistoryEntry;
+use crate::run::result::RunResult;
+use crate::run::settings::{RunManager, SavedCommand};
+use crate::variables::builder::VariablesBuilder;
+use crate::variables::placeholder::Placeholder;
+use crate::workspaces::settings::WorkspacesManager;
+
+use super::settings::{Flow, FlowsManager};
+
+#[derive(clap::Subcommand, Debug)]
+pub enum FlowCommand {
+    List,
+    /// Create or replace a flow that runs saved commands in the given order
+    Create {
+        name: String,
+
+        /// Ids of the saved commands to run
+        #[arg(required = true)]
+        steps: Vec<String>,
+
+        /// Keep running the remaining steps when one fails
+        #[arg(long, default_value = "false")]
+        continue_on_failure: bool,
+    },
+    Show {
+        name: String,
+    },
+    Delete {
+        name: String,
+    },
+    Run {
+        name: String,
+
+        #[command(flatten)]
+        vars: VarsInput,
+    },
+}
+
+enum StepOutcome {
+    Passed(RunResult),
+    Failed(Option<RunResult>, String),
+    Skipped,
+}
+
+impl FlowCommand {
+    pub fn cli_match(
+        command: &FlowCommand,
+        flows_manager: &mut FlowsManager,
+        run_settings: &mut RunManager,
+        variables_builder: &mut VariablesBuilder,
+        workspaces_manager: &mut WorkspacesManager,
+        prompt_manager: &mut PromptManager,
+    ) -> ExitCode {
+        match command {
+            FlowCommand::List => {
+                IO::output(&flows_manager.get_flow_names().join("\n"));
+                ExitCode::SUCCESS
+            }
+            FlowCommand::Create {
+                name,
+                steps,
+                continue_on_failure,
+            } => {
+                let missing: Vec<&String> = steps
+                    .iter()
+                    .filter(|id| run_settings.get_saved(id).is_none())
+                    .collect();
+                if !missing.is_empty() {
+                    IO::error(&format!(
+                        "Could not find saved commands: {}",
+                        missing
+                            .iter()
+                            .map(|id| id.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    ));
+                    return ExitCode::FAILURE;
+                }
+
+                flows_manager.set_flow(
+                    name,
+                    Flow {
+                        steps: steps.to_owned(),
+                        continue_on_failure: *continue_on_failure,
+                    },
+                );
+                IO::output(&format!("Flow created: {}", name));
+                ExitCode::SUCCESS
+            }
+            FlowCommand::Show { name } => match flows_manager.get_flow(name) {
+                Some(flow) => {
+                    for (index, id) in flow.steps.iter().enumerate() {
+                        IO::output(&format!("{}| {}", index + 1, id));
+                    }
+                    if flow.continue_on_failure {
+                        IO::output("Continues on failure");
+                    }
+                    ExitCode::SUCCESS
+                }
+                None => FlowCommand::not_found(name),
+            },
+            FlowCommand::Delete { name } => match flows_manager.remove_flow(name) {
+                Some(_) => {
+                    IO::output(&format!("Flow deleted: {}", name));
+                    ExitCode::SUCCESS
+                }
+                None => FlowCommand::not_found(name),
+            },
+            FlowCommand::Run { name, vars } => match flows_manager.get_flow(name) {
+                Some(flow) => FlowCommand::run(
+                    name,
+                    flow,
+                    vars,
+                    run_settings,
+                    variables_builder,
+                    workspaces_manager,
+                    prompt_manager,
+                ),
+                None => FlowCommand::not_found(name),
+            },
+        }
+    }
+
+    fn not_found(name: &str) -> ExitCode {
+        IO::error(&format!("Could not find flow {}", name));
+        ExitCode::FAILURE
+    }
+
+    fn run(
+        name: &str,
+        flow: &Flow,
+        vars: &VarsInput,
+        run_settings: &mut RunManager,
+        variables_builder: &mut VariablesBuilder,
+        workspaces_manager: &mut WorkspacesManager,
+        prompt_manager: &mut PromptManager,
+    ) -> ExitCode {
+        // r{} values given up front or used by a step are reused by every later step
+        let mut shared_values: HashMap<String, String> = match vars.values() {
+            Ok(values) => values,
+            Err(message) => {
+                IO::error(&message);
+                return ExitCode::FAILURE;
+            }
+        };
+        let mut outcomes: Vec<StepOutcome> = Vec::new();
+
+        for (index, id) in flow.steps.iter().enumerate() {
+            let stop = outcomes
+                .iter()
+                .any(|outcome| matches!(outcome, StepOutcome::Failed(..)))
+                && !flow.continue_on_failure;
+            if stop {
+                outcomes.push(StepOutcome::Skipped);
+                continue;
+            }
+
+            IO::output(&format!("==> [{}/{}] {}", index + 1, flow.steps.len(), id));
+
+            let saved = match run_settings.get_saved(id) {
+                Some(saved) => saved.clone(),
+                None => {
+                    let message = format!("Could not find saved command {}", id);
+                    IO::error(&message);
+                    outcomes.push(StepOutcome::Failed(None, message));
+                    continue;
+                }
+            };
+
+            let keys = match variables_builder.extract(&saved.command.user_args) {
+                Ok(keys) => keys,
+                Err(error) => {
+                    IO::error(&error.to_string());
+                    outcomes.push(StepOutcome::Failed(None, error.message()));
+                    continue;
+                }
+            };
+            // Only values the step uses are kept with it, so given secrets stay out of the history
+            let mut values = saved.command.value_map.clone();
+            values.extend(
+                shared_values
+                    .iter()
+                    .filter(|(key, _)| keys.iter().any(|placeholder| &placeholder.name == *key))
+                    .map(|(key, value)| (key.to_owned(), value.to_owned())),
+            );
+
+            let missing: Vec<Placeholder> = keys
+                .iter()
+                .filter(|key| !values.contains_key(&key.name))
+                .cloned()
+                .collect();
+            let mut prompted = HashMap::new();
+            if let Err(message) = RunCommand::loop_prompt(&missing, &mut prompted, prompt_manager) {
+                IO::error(&message);
+                outcomes.push(StepOutcome::Failed(None, message));
+                continue;
+            }
+            values.extend(prompted);
+            // Every value the step used is reused by later steps, including ones saved with it
+            shared_values.extend(keys.iter().filter_map(|key| {
+                values
+                    .get(&key.name)
+                    .map(|value| (key.name.to_owned(), value.to_owned()))
+            }));
+            if let Err(message) = variables_builder.fill(&shared_values) {
+                IO::error(&message);
+                outcomes.push(StepOutcome::Failed(None, message));
+                continue;
+            }
+
+            let curld_cmd = CurldCommand::new(saved.command.user_args.to_owned(), values);
+            let result = RunCommand::run(
+                variables_builder.insert(&curld_cmd),
+                &OutputInput::default(),
+            );
+            run_settings.insert_history(HistoryEntry::new(curld_cmd, result.as_ref().ok()));
+
+            outcomes.push(match result {
+                Ok(result) => {
+                    RunCommand::apply_captures(
+                        &saved,
+                        &result,
+                        workspaces_manager,
+                        variables_builder,
+                    );
+                    match FlowCommand::step_failure(&saved, &result) {
+                        Some(message) => StepOutcome::Failed(Some(result), message),
+                        None => StepOutcome::Passed(result),
+                    }
+                }
+                Err(message) => {
+                    IO::error(&message);
+                    StepOutcome::Failed(None, message)
+                }
+            });
+        }
+
+        FlowCommand::summarize(name, flow, &outcomes)
+    }
+
+    /// A step fails when curl fails, when its expectations aren't met or, without
+    /// expectations, when the server responds with an error status.
+    fn step_failure(saved: &SavedCommand, result: &RunResult) -> Option<String> {
+        if saved.expect.is_empty() {
+            return result.failure();
+        }
+
+        if let Some(message) = result.error_message() {
+            return Some(message);
+        }
+
+        let failures: Vec<String> = saved
+            .expect
+            .check(result)
+            .into_iter()
+            .filter_map(|outcome| {
+                outcome
+                    .failure
+                    .map(|failure| format!("{}: {}", outcome.description, failure))
+            })
+            .collect();
+
+        match failures.is_empty() {
+            true => None,
+            false => Some(failures.join(", ")),
+        }
+    }
+
+    fn summarize(name: &str, flow: &Flow, outcomes: &[StepOutcome]) -> ExitCode {
+        let count =
+            |matches: fn(&StepOutcome) -> bool| outcomes.iter().filter(|o| matches(o)).count();
+        let failed = count(|outcome| matches!(outcome, StepOutcome::Failed(..)));
+
+        IO::output(&format!(
+            "\nFlow {}: {} passed, {} failed, {} skipped",
+            name,
+            count(|outcome| matches!(outcome, StepOutcome::Passed(_))),
+            failed,
+            count(|outcome| matches!(outcome, StepOutcome::Skipped))
+        ));
+
+        let width = flow.steps.iter().map(|id| id.len()).max().unwrap_or(0);
+        for (id, outcome) in flow.steps.iter().zip(outcomes) {
+            let line = match outcome {
+                StepOutcome::Passed(result) => {
+                    format!("  ok    {:width$}  {}", id, result.status_row())
+                }
+                StepOutcome::Failed(Some(result), message) => format!(
+                    "  FAIL  {:width$}  {}  {}",
+                    id,
+                    result.status_row(),
+                    message
+                ),
+                StepOutcome::Failed(None, message) => format!("  FAIL  {:width$}  {}", id, message),
+                StepOutcome::Skipped => format!("  skip  {}", id),
+            };
+            IO::output(line.trim_end());
+        }
+
+        if failed > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::settings::traits::StoredSettings;
+
+pub static FLOW_MODULE: &str = "flow";
+
+pub struct FlowsManager<'a> {
+    stored_settings: &'a RefCell<dyn StoredSettings<FlowSettings>>,
+    flow_settings: FlowSettings,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct FlowSettings {
+    #[serde(default)]
+    flows: HashMap<String, Flow>,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct Flow {
+    // Ids of saved commands, run in order
+    pub steps: Vec<String>,
+
+    #[serde(default)]
+    pub continue_on_failure: bool,
+}
+
+impl<'a> FlowsManager<'a> {
+    pub fn new(stored_settings: &'a RefCell<dyn StoredSettings<FlowSettings>>) -> Self {
+        let flow_settings = stored_settings
+            .borrow()
+            .get_module(FLOW_MODULE)
+            .unwrap_or_default();
+
+        Self {
+            stored_settings,
+            flow_settings,
+        }
+    }
+
+    pub fn get_flow(&self, name: &str) -> Option<&Flow> {
+        self.flow_settings.flows.get(name)
+    }
+
+    pub fn get_flow_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.flow_settings.flows.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn set_flow(&mut self, name: &str, flow: Flow) {
+        self.flow_settings.flows.insert(name.to_string(), flow);
+        self.save_to_parent();
+    }
+
+    pub fn remove_flow(&mut self, name: &str) -> Option<Flow> {
+        let removed = self.flow_settings.flows.remove(name);
+        self.save_to_parent();
+        removed
+    }
+
+    fn save_to_parent(&mut self) {
+        self.stored_settings
+            .borrow_mut()
+            .insert_module(FLOW_MODULE, &self.flow_settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::traits::MockStoredSettings;
+
+    fn stored_settings() -> RefCell<MockStoredSettings<FlowSettings>> {
+        let stored_settings = RefCell::new(MockStoredSettings::new());
+        stored_settings
+            .borrow_mut()
+            .expect_get_module()
+            .returning(|_| None);
+        stored_settings
+            .borrow_mut()
+            .expect_insert_module()
+            .returning(|_, _| ());
+
+        stored_settings
+    }
+
+    #[test]
+    fn set_flow_should_replace_existing_flow() {
+        let stored_settings = stored_settings();
+        let mut manager = FlowsManager::new(&stored_settings);
+
+        manager.set_flow("orders", Flow::default());
+        manager.set_flow(
+            "orders",
+            Flow {
+                steps: vec!["users/create".to_string(), "orders/create".to_string()],
+                continue_on_failure: true,
+            },
+        );
+
+        assert_eq!(manager.get_flow_names(), vec!["orders".to_string()]);
+        assert_eq!(manager.get_flow("orders").unwrap().steps.len(), 2);
+    }
+
+    #[test]
+    fn remove_flow_should_return_removed_flow() {
+        let stored_settings = stored_settings();
+        let mut manager = FlowsManager::new(&stored_settings);
+        manager.set_flow("orders", Flow::default());
+
+        assert!(manager.remove_flow("orders").is_some());
+        assert!(manager.get_flow("orders").is_none());
+    }
+}
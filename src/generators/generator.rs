@@ -0,0 +1,149 @@
+use chrono::{format::Item, format::StrftimeItems, SecondsFormat, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use uuid::Uuid;
+
+/// A value made fresh each time a command runs, like `f{uuid}` or `f{random_int:1:100}`
+#[derive(Debug, PartialEq)]
+pub enum Generator {
+    Uuid,
+    Now(TimeFormat),
+    UnixMs,
+    RandomInt(i64, i64),
+    RandomString(usize),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TimeFormat {
+    Rfc3339,
+    Rfc2822,
+    // Any other format is read as strftime, like `%Y-%m-%d`
+    Custom(String),
+}
+
+impl Generator {
+    /// Parses what is between the braces, the generator's name followed by its arguments
+    pub fn parse(body: &str) -> Result<Self, String> {
+        let (name, args) = match body.split_once(':') {
+            Some((name, args)) => (name.trim(), Some(args)),
+            None => (body.trim(), None),
+        };
+
+        match (name, args) {
+            ("uuid", None) => Ok(Generator::Uuid),
+            ("unix_ms", None) => Ok(Generator::UnixMs),
+            ("now", None) => Ok(Generator::Now(TimeFormat::Rfc3339)),
+            // strftime formats can contain colons, so everything after the name is the format
+            ("now", Some(format)) => TimeFormat::parse(format).map(Generator::Now),
+            ("random_int", args) => {
+                let bounds = args.and_then(|args| {
+                    let (min, max) = args.split_once(':')?;
+                    Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+                });
+
+                match bounds {
+                    Some((min, max)) if min <= max => Ok(Generator::RandomInt(min, max)),
+                    Some(_) => Err("random_int needs its minimum before its maximum".to_string()),
+                    None => Err(
+                        "random_int takes a minimum and maximum, like random_int:1:100".to_string(),
+                    ),
+                }
+            }
+            ("random_string", args) => args
+                .and_then(|length| length.trim().parse().ok())
+                .map(Generator::RandomString)
+                .ok_or_else(|| "random_string takes a length, like random_string:16".to_string()),
+            ("uuid" | "unix_ms", Some(_)) => Err(format!("{} doesn't take arguments", name)),
+            _ => Err(format!(
+                "unknown generator {}, expected one of uuid, now, unix_ms, random_int or random_string",
+                name
+            )),
+        }
+    }
+
+    pub fn generate(&self) -> String {
+        match self {
+            Generator::Uuid => Uuid::new_v4().to_string(),
+            Generator::Now(format) => format.now(),
+            Generator::UnixMs => Utc::now().timestamp_millis().to_string(),
+            Generator::RandomInt(min, max) => rand::thread_rng().gen_range(*min..=*max).to_string(),
+            Generator::RandomString(length) => rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(*length)
+                .map(char::from)
+                .collect(),
+        }
+    }
+}
+
+impl TimeFormat {
+    fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "rfc3339" => Ok(TimeFormat::Rfc3339),
+            "rfc2822" => Ok(TimeFormat::Rfc2822),
+            _ if StrftimeItems::new(format).any(|item| item == Item::Error) => {
+                Err(format!("{} is not a valid time format", format))
+            }
+            _ => Ok(TimeFormat::Custom(format.to_string())),
+        }
+    }
+
+    fn now(&self) -> String {
+        let now = Utc::now();
+        match self {
+            TimeFormat::Rfc3339 => now.to_rfc3339_opts(SecondsFormat::Secs, true),
+            TimeFormat::Rfc2822 => now.to_rfc2822(),
+            TimeFormat::Custom(format) => now.format(format).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+
+    #[test]
+    fn parse_should_read_arguments() {
+        assert_eq!(Generator::parse("uuid"), Ok(Generator::Uuid));
+        assert_eq!(
+            Generator::parse("now:rfc3339"),
+            Ok(Generator::Now(TimeFormat::Rfc3339))
+        );
+        assert_eq!(
+            Generator::parse("now:%H:%M"),
+            Ok(Generator::Now(TimeFormat::Custom("%H:%M".to_string())))
+        );
+        assert_eq!(
+            Generator::parse("random_int:1:100"),
+            Ok(Generator::RandomInt(1, 100))
+        );
+        assert_eq!(
+            Generator::parse("random_string:16"),
+            Ok(Generator::RandomString(16))
+        );
+    }
+
+    #[test]
+    fn parse_should_reject_bad_generators() {
+        assert!(Generator::parse("nope").is_err());
+        assert!(Generator::parse("random_int:100:1").is_err());
+        assert!(Generator::parse("random_int").is_err());
+        assert!(Generator::parse("random_string:long").is_err());
+        assert!(Generator::parse("uuid:4").is_err());
+        assert!(Generator::parse("now:%Q").is_err());
+    }
+
+    #[test]
+    fn generate_should_make_values_in_shape() {
+        assert_eq!(Generator::Uuid.generate().len(), 36);
+        assert_ne!(Generator::Uuid.generate(), Generator::Uuid.generate());
+        assert!(
+            DateTime::parse_from_rfc3339(&Generator::Now(TimeFormat::Rfc3339).generate()).is_ok()
+        );
+        assert_eq!(Generator::RandomString(16).generate().len(), 16);
+
+        let value: i64 = Generator::RandomInt(1, 3).generate().parse().unwrap();
+        assert!((1..=3).contains(&value));
+    }
+}
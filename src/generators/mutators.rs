@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use crate::variables::{
+    lexer::{tokenize, TemplateError, Token},
+    placeholder::Placeholder,
+    Extractor, Inserter,
+};
+
+use super::generator::Generator;
+
+static OPENING: &str = "f{";
+static CLOSING: &str = "}";
+
+/// Fills `f{uuid}` and the other generators with a new value every time it runs
+pub struct GeneratorMutator {}
+
+impl Inserter for GeneratorMutator {
+    fn opening(&self) -> &str {
+        OPENING
+    }
+
+    fn value(
+        &self,
+        placeholder: &Placeholder,
+        _value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        Generator::parse(&placeholder.definition())
+            .ok()
+            .map(|generator| generator.generate())
+    }
+
+    // A value shown later would not be the one that was sent
+    fn display(
+        &self,
+        _placeholder: &Placeholder,
+        _value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        None
+    }
+}
+
+impl Extractor for GeneratorMutator {
+    // Nothing is prompted for, this only checks every generator can be used
+    fn extract(&self, template: &str) -> Result<Vec<Placeholder>, TemplateError> {
+        for token in tokenize(template, OPENING, CLOSING)? {
+            if let Token::Placeholder {
+                placeholder,
+                column,
+                ..
+            } = token
+            {
+                Generator::parse(&placeholder.definition())
+                    .map_err(|message| TemplateError::at_column(template, column, message))?;
+            }
+        }
+
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variables::template::Template;
+
+    fn insert(template: &str) -> String {
+        Template::compile(template, &[OPENING], CLOSING)
+            .unwrap()
+            .render(&|_, placeholder| GeneratorMutator {}.value(placeholder, &HashMap::new()))
+    }
+
+    #[test]
+    fn inserter_should_generate_each_occurrence() {
+        let inserted = insert("f{random_string:8}-f{random_string:8}-f{{uuid}");
+
+        let (first, rest) = inserted.split_once('-').unwrap();
+        let (second, literal) = rest.split_once('-').unwrap();
+        assert_eq!(first.len(), 8);
+        assert_ne!(first, second);
+        assert_eq!(literal, "f{uuid}");
+    }
+
+    #[test]
+    fn inserter_should_filter_generated_values() {
+        let inserted = insert("f{random_string:12|upper}");
+
+        assert_eq!(inserted.len(), 12);
+        assert_eq!(inserted, inserted.to_uppercase());
+    }
+
+    #[test]
+    fn extractor_should_point_at_unknown_generator() {
+        let error = GeneratorMutator {}
+            .extract("id=f{uuid}&at=f{later}")
+            .unwrap_err();
+
+        assert_eq!(error.column, 15);
+    }
+}
@@ -1,11 +1,18 @@
 mod cli;
+// Not wired into the cli yet
+#[allow(dead_code)]
 mod command;
 mod common;
+mod env;
+mod flows;
+mod generators;
+mod prompt;
 mod run;
+mod secrets;
 mod settings;
 mod variables;
 mod workspaces;
 
-fn main() {
-    cli::run();
+fn main() -> std::process::ExitCode {
+    cli::run()
 }
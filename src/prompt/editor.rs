@@ -0,0 +1,285 @@
+use termion::event::Key;
+
+/// The line being typed at a prompt, changed one key at a time. Drawing it is left to
+/// the caller so the editing can be followed without a terminal.
+pub struct LineEditor<'a> {
+    line: Vec<char>,
+    cursor: usize,
+
+    // Values entered before for the same key, the latest first
+    history: &'a [String],
+    // Where up and down have moved to in the history, and what was typed before moving
+    browsing: Option<usize>,
+    draft: Vec<char>,
+
+    completions: &'a [String],
+    search: Option<Search>,
+}
+
+struct Search {
+    query: String,
+    // How many matches Ctrl-R has skipped past
+    skipped: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Editing,
+    Done(String),
+    Cancelled,
+}
+
+impl<'a> LineEditor<'a> {
+    pub fn new(initial: &str, history: &'a [String], completions: &'a [String]) -> Self {
+        let line: Vec<char> = initial.chars().collect();
+        Self {
+            cursor: line.len(),
+            line,
+            history,
+            browsing: None,
+            draft: Vec::new(),
+            completions,
+            search: None,
+        }
+    }
+
+    pub fn handle(&mut self, key: Key) -> Outcome {
+        if self.search.is_some() {
+            return self.handle_search(key);
+        }
+
+        match key {
+            Key::Char('\n') => return Outcome::Done(self.line()),
+            Key::Ctrl('c') => return Outcome::Cancelled,
+            Key::Char('\t') => self.complete(),
+            Key::Char(character) => {
+                self.line.insert(self.cursor, character);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+            }
+            Key::Delete | Key::Ctrl('d') if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+            }
+            Key::Left | Key::Ctrl('b') => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right | Key::Ctrl('f') => self.cursor = (self.cursor + 1).min(self.line.len()),
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = self.line.len(),
+            Key::Ctrl('u') => {
+                self.line.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::Up => self.browse(self.browsing.map_or(0, |index| index + 1)),
+            Key::Down => match self.browsing {
+                Some(0) => {
+                    self.browsing = None;
+                    self.set_line(self.draft.clone());
+                }
+                Some(index) => self.browse(index - 1),
+                None => (),
+            },
+            Key::Ctrl('r') => {
+                self.search = Some(Search {
+                    query: String::new(),
+                    skipped: 0,
+                })
+            }
+            _ => (),
+        }
+
+        Outcome::Editing
+    }
+
+    // While searching keys change the query, and anything that moves takes the match
+    fn handle_search(&mut self, key: Key) -> Outcome {
+        let search = self.search.as_mut().expect("only called while searching");
+        match key {
+            Key::Char('\n') => {
+                let found = self.found().map(String::to_owned);
+                self.search = None;
+                return Outcome::Done(found.unwrap_or_else(|| self.line()));
+            }
+            Key::Ctrl('c') => return Outcome::Cancelled,
+            Key::Ctrl('r') => {
+                search.skipped += 1;
+                if self.found().is_none() {
+                    // Stays on the oldest match rather than losing it
+                    self.search.as_mut().expect("searching").skipped -= 1;
+                }
+            }
+            Key::Char(character) => {
+                search.query.push(character);
+                search.skipped = 0;
+            }
+            Key::Backspace => {
+                search.query.pop();
+                search.skipped = 0;
+            }
+            Key::Esc | Key::Ctrl('g') => self.search = None,
+            _ => {
+                if let Some(found) = self.found().map(String::to_owned) {
+                    self.set_line(found.chars().collect());
+                }
+                self.search = None;
+            }
+        }
+
+        Outcome::Editing
+    }
+
+    fn browse(&mut self, index: usize) {
+        let Some(value) = self.history.get(index) else {
+            return;
+        };
+
+        if self.browsing.is_none() {
+            self.draft = self.line.clone();
+        }
+        self.browsing = Some(index);
+        self.set_line(value.chars().collect());
+    }
+
+    // Completes the text before the cursor as far as the matching values agree
+    fn complete(&mut self) {
+        let typed: String = self.line[..self.cursor].iter().collect();
+        let matches: Vec<&String> = self
+            .completions
+            .iter()
+            .filter(|completion| completion.starts_with(&typed))
+            .collect();
+
+        let Some(first) = matches.first() else {
+            return;
+        };
+        let common: Vec<char> = first
+            .chars()
+            .enumerate()
+            .take_while(|(index, character)| {
+                matches
+                    .iter()
+                    .all(|completion| completion.chars().nth(*index) == Some(*character))
+            })
+            .map(|(_, character)| character)
+            .collect();
+
+        let rest = self.line.split_off(self.cursor);
+        self.line = common;
+        self.cursor = self.line.len();
+        self.line.extend(rest);
+    }
+
+    fn set_line(&mut self, line: Vec<char>) {
+        self.cursor = line.len();
+        self.line = line;
+    }
+
+    pub fn line(&self) -> String {
+        self.line.iter().collect()
+    }
+
+    /// Where the cursor is, counted in characters
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The Ctrl-R query, while searching
+    pub fn query(&self) -> Option<&str> {
+        self.search.as_ref().map(|search| search.query.as_str())
+    }
+
+    /// The value the Ctrl-R query matches, while searching
+    pub fn found(&self) -> Option<&String> {
+        let search = self.search.as_ref()?;
+        self.history
+            .iter()
+            .filter(|value| value.contains(&search.query))
+            .nth(search.skipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn type_keys(editor: &mut LineEditor, keys: &[Key]) -> Outcome {
+        let mut outcome = Outcome::Editing;
+        for key in keys {
+            outcome = editor.handle(*key);
+        }
+        outcome
+    }
+
+    #[test]
+    fn editor_should_insert_and_delete_at_cursor() {
+        let mut editor = LineEditor::new("ac", &[], &[]);
+
+        let outcome = type_keys(
+            &mut editor,
+            &[
+                Key::Left,
+                Key::Char('b'),
+                Key::End,
+                Key::Char('d'),
+                Key::Home,
+                Key::Delete,
+                Key::Right,
+                Key::Backspace,
+                Key::Char('\n'),
+            ],
+        );
+
+        assert_eq!(outcome, Outcome::Done("cd".to_string()));
+    }
+
+    #[test]
+    fn up_and_down_should_cycle_through_history() {
+        let history = strings(&["latest", "older"]);
+        let mut editor = LineEditor::new("draft", &history, &[]);
+
+        type_keys(&mut editor, &[Key::Up, Key::Up, Key::Up]);
+        assert_eq!(editor.line(), "older");
+
+        type_keys(&mut editor, &[Key::Down]);
+        assert_eq!(editor.line(), "latest");
+
+        type_keys(&mut editor, &[Key::Down, Key::Down]);
+        assert_eq!(editor.line(), "draft");
+    }
+
+    #[test]
+    fn ctrl_r_should_search_history() {
+        let history = strings(&["staging.example.com", "dev.example.com", "prod.internal"]);
+        let mut editor = LineEditor::new("", &history, &[]);
+
+        type_keys(
+            &mut editor,
+            &[Key::Ctrl('r'), Key::Char('e'), Key::Char('x')],
+        );
+        assert_eq!(editor.found(), Some(&history[0]));
+
+        type_keys(&mut editor, &[Key::Ctrl('r'), Key::Ctrl('r')]);
+        assert_eq!(editor.found(), Some(&history[1]));
+
+        type_keys(&mut editor, &[Key::Right, Key::Char('/')]);
+        assert_eq!(editor.query(), None);
+        assert_eq!(editor.line(), "dev.example.com/");
+    }
+
+    #[test]
+    fn tab_should_complete_common_prefix() {
+        let completions = strings(&["https://api.example.com", "https://app.example.com", "v2"]);
+        let mut editor = LineEditor::new("ht", &[], &completions);
+
+        type_keys(&mut editor, &[Key::Char('\t')]);
+        assert_eq!(editor.line(), "https://ap");
+
+        type_keys(&mut editor, &[Key::Char('i'), Key::Char('\t')]);
+        assert_eq!(editor.line(), "https://api.example.com");
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+};
+
+use crate::{common::IO, settings::traits::StoredSettings};
+
+pub static PROMPT_MODULE: &str = "prompt";
+
+// How many values are kept for each key
+static HISTORY_LEN: usize = 20;
+
+pub struct PromptManager<'a> {
+    stored_settings: &'a RefCell<dyn StoredSettings<PromptSettings>>,
+    prompt_settings: PromptSettings,
+
+    // Offered with tab, such as the values of the current workspace
+    completions: Vec<String>,
+}
+
+// Names of values that are never kept, matched anywhere in the key ignoring case
+static SENSITIVE: &[&str] = &["password", "passwd", "secret", "token", "api_key", "apikey"];
+
+#[derive(Deserialize, Serialize)]
+pub struct PromptSettings {
+    // Set to false to stop keeping entered values
+    #[serde(default = "keeps_history")]
+    save_history: bool,
+
+    // Values entered for each key, the latest first
+    #[serde(default)]
+    history: HashMap<String, VecDeque<String>>,
+}
+
+fn keeps_history() -> bool {
+    true
+}
+
+impl Default for PromptSettings {
+    fn default() -> Self {
+        Self {
+            save_history: keeps_history(),
+            history: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> PromptManager<'a> {
+    pub fn new(
+        stored_settings: &'a RefCell<dyn StoredSettings<PromptSettings>>,
+        completions: Vec<String>,
+    ) -> Self {
+        let prompt_settings = stored_settings
+            .borrow()
+            .get_module(PROMPT_MODULE)
+            .unwrap_or_default();
+
+        let mut completions = completions;
+        completions.sort();
+        completions.dedup();
+
+        Self {
+            stored_settings,
+            prompt_settings,
+            completions,
+        }
+    }
+
+    /// Asks for the value of `key` on a line that can be edited, starting out as `initial`
+    pub fn read(&self, key: &str, message: &str, initial: &str) -> String {
+        IO::read_line(message, initial, &self.get_history(key), &self.completions)
+    }
+
+    pub fn get_history(&self, key: &str) -> Vec<String> {
+        if !self.prompt_settings.save_history {
+            return Vec::new();
+        }
+
+        self.prompt_settings
+            .history
+            .get(key)
+            .map(|values| values.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Keeps a value entered for `key`, unless history is turned off or the key names
+    /// something like a password or token
+    pub fn add_history(&mut self, key: &str, value: &str) {
+        if value.is_empty() || !self.prompt_settings.save_history || PromptManager::sensitive(key) {
+            return;
+        }
+
+        let values = self
+            .prompt_settings
+            .history
+            .entry(key.to_string())
+            .or_default();
+        // A value entered again moves back to the front
+        values.retain(|existing| existing != value);
+        values.push_front(value.to_string());
+        values.truncate(HISTORY_LEN);
+
+        self.save_to_parent();
+    }
+
+    fn sensitive(key: &str) -> bool {
+        let key = key.to_lowercase().replace('-', "_");
+        SENSITIVE.iter().any(|name| key.contains(name))
+    }
+
+    fn save_to_parent(&mut self) {
+        self.stored_settings
+            .borrow_mut()
+            .insert_module(PROMPT_MODULE, &self.prompt_settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::traits::MockStoredSettings;
+
+    #[test]
+    fn add_history_should_keep_latest_value_first_once() {
+        let stored_settings = RefCell::new(MockStoredSettings::new());
+        stored_settings
+            .borrow_mut()
+            .expect_get_module()
+            .returning(|_| None);
+        stored_settings
+            .borrow_mut()
+            .expect_insert_module()
+            .returning(|_, _| ());
+        let mut manager = PromptManager::new(&stored_settings, Vec::new());
+
+        for value in ["dev", "staging", "", "dev"] {
+            manager.add_history("env", value);
+        }
+        for index in 0..25 {
+            manager.add_history("page", &index.to_string());
+        }
+
+        assert_eq!(manager.get_history("env"), vec!["dev", "staging"]);
+        assert_eq!(manager.get_history("page").len(), HISTORY_LEN);
+        assert_eq!(manager.get_history("page")[0], "24");
+        assert!(manager.get_history("id").is_empty());
+    }
+
+    #[test]
+    fn add_history_should_skip_sensitive_keys_and_respect_the_switch() {
+        let stored_settings = RefCell::new(MockStoredSettings::new());
+        stored_settings
+            .borrow_mut()
+            .expect_get_module()
+            .returning(|_| None);
+        stored_settings
+            .borrow_mut()
+            .expect_insert_module()
+            .returning(|_, _| ());
+        let mut manager = PromptManager::new(&stored_settings, Vec::new());
+
+        manager.add_history("db_password", "hunter2");
+        manager.add_history("Access-Token", "abc");
+        assert!(manager.get_history("db_password").is_empty());
+        assert!(manager.get_history("Access-Token").is_empty());
+
+        manager.prompt_settings.save_history = false;
+        manager.add_history("env", "dev");
+        assert!(manager.get_history("env").is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::json_path;
+use super::result::RunResult;
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+pub struct Expectations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<HeaderExpectation>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub json: Vec<JsonExpectation>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_latency_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct HeaderExpectation {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// Checks that the value at a JSON path exists, and equals a value when one is given
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct JsonExpectation {
+    pub path: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<Value>,
+}
+
+pub struct Outcome {
+    pub description: String,
+    pub failure: Option<String>,
+}
+
+impl Outcome {
+    fn new(description: String, failure: Option<String>) -> Self {
+        Self {
+            description,
+            failure,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl HeaderExpectation {
+    /// Parses `name` or `name: value`
+    pub fn parse(input: &str) -> Self {
+        match input.split_once(':') {
+            Some((name, value)) => Self {
+                name: name.trim().to_string(),
+                value: Some(value.trim().to_string()),
+            },
+            None => Self {
+                name: input.trim().to_string(),
+                value: None,
+            },
+        }
+    }
+}
+
+impl JsonExpectation {
+    pub fn exists(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            equals: None,
+        }
+    }
+
+    /// Parses `path=value`, where the value is read as JSON and falls back to a string
+    pub fn parse_equals(input: &str) -> Result<Self, String> {
+        let (path, value) = input
+            .split_once('=')
+            .ok_or_else(|| format!("Expected `path=value` but got `{}`", input))?;
+
+        let equals = serde_json::from_str(value.trim())
+            .unwrap_or_else(|_| Value::String(value.trim().to_string()));
+
+        Ok(Self {
+            path: path.trim().to_string(),
+            equals: Some(equals),
+        })
+    }
+
+    fn check(&self, body: &Result<Value, String>) -> Outcome {
+        let description = match &self.equals {
+            Some(expected) => format!("{} is {}", self.path, expected),
+            None => format!("{} exists", self.path),
+        };
+
+        let failure = body
+            .as_ref()
+            .map_err(|error| error.to_owned())
+            .and_then(|body| json_path::select(body, &self.path))
+            .map_or_else(Some, |actual| match (actual, &self.equals) {
+                (None, _) => Some("path not found".to_string()),
+                (Some(actual), Some(expected)) if actual != expected => {
+                    Some(format!("got {}", actual))
+                }
+                _ => None,
+            });
+
+        Outcome::new(description, failure)
+    }
+}
+
+impl Expectations {
+    pub fn is_empty(&self) -> bool {
+        *self == Expectations::default()
+    }
+
+    /// Checks every JSON path can be read, so a mistake is reported when the expectations
+    /// are set rather than when they are tested
+    pub fn validate(&self) -> Result<(), String> {
+        self.json
+            .iter()
+            .try_for_each(|expectation| json_path::validate(&expectation.path))
+    }
+
+    pub fn check(&self, result: &RunResult) -> Vec<Outcome> {
+        let mut outcomes = vec![Outcome::new(
+            "request completed".to_string(),
+            result.error_message(),
+        )];
+        let response = result.response.clone().unwrap_or_default();
+
+        if let Some(expected) = self.status {
+            let failure = match response.status_code() {
+                Some(code) if code == expected => None,
+                Some(code) => Some(format!("got {}", code)),
+                None => Some("no status was received".to_string()),
+            };
+            outcomes.push(Outcome::new(format!("status is {}", expected), failure));
+        }
+
+        for header in &self.headers {
+            let actual = response.header(&header.name);
+            let (description, failure) = match &header.value {
+                Some(expected) => (
+                    format!("header {} is {}", header.name, expected),
+                    match actual {
+                        Some(value) if value == expected => None,
+                        Some(value) => Some(format!("got {}", value)),
+                        None => Some("header is missing".to_string()),
+                    },
+                ),
+                None => (
+                    format!("header {} is present", header.name),
+                    actual.map_or(Some("header is missing".to_string()), |_| None),
+                ),
+            };
+            outcomes.push(Outcome::new(description, failure));
+        }
+
+        if !self.json.is_empty() {
+            let body = serde_json::from_slice::<Value>(&result.stdout)
+                .map_err(|error| format!("body is not JSON: {}", error));
+
+            for expectation in &self.json {
+                outcomes.push(expectation.check(&body));
+            }
+        }
+
+        if let Some(max_latency) = self.max_latency_ms {
+            let latency = match &response.metrics {
+                Some(metrics) => (metrics.time_total * 1000.0) as u128,
+                None => result.duration.as_millis(),
+            };
+            let failure = if latency > max_latency as u128 {
+                Some(format!("took {}ms", latency))
+            } else {
+                None
+            };
+            outcomes.push(Outcome::new(
+                format!("responds within {}ms", max_latency),
+                failure,
+            ));
+        }
+
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::run::response::{ResponseMeta, Status};
+
+    fn result(code: u16, body: &str) -> RunResult {
+        RunResult::new(
+            Some(0),
+            Duration::from_millis(30),
+            body.as_bytes().to_vec(),
+            Vec::new(),
+        )
+        .with_response(Some(ResponseMeta {
+            status: Some(Status {
+                version: "HTTP/1.1".to_string(),
+                code,
+                reason: String::new(),
+            }),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            metrics: None,
+        }))
+    }
+
+    fn failures(expectations: &Expectations, result: &RunResult) -> Vec<String> {
+        expectations
+            .check(result)
+            .into_iter()
+            .filter_map(|outcome| {
+                outcome
+                    .failure
+                    .map(|f| format!("{}: {}", outcome.description, f))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_should_pass_matching_response() {
+        let expectations = Expectations {
+            status: Some(200),
+            headers: vec![HeaderExpectation::parse("content-type: application/json")],
+            json: vec![
+                JsonExpectation::parse_equals("$.user.name=\"ada\"").unwrap(),
+                JsonExpectation::exists("$.user.id"),
+            ],
+            max_latency_ms: Some(100),
+        };
+
+        let result = result(200, r#"{ "user": { "id": 1, "name": "ada" } }"#);
+
+        assert_eq!(failures(&expectations, &result), Vec::<String>::new());
+    }
+
+    #[test]
+    fn check_should_report_each_failure() {
+        let expectations = Expectations {
+            status: Some(201),
+            headers: vec![HeaderExpectation::parse("x-request-id")],
+            json: vec![JsonExpectation::parse_equals("$.count=2").unwrap()],
+            max_latency_ms: Some(10),
+        };
+
+        let result = result(500, r#"{ "count": 1 }"#);
+
+        assert_eq!(
+            failures(&expectations, &result),
+            vec![
+                "status is 201: got 500",
+                "header x-request-id is present: header is missing",
+                "$.count is 2: got 1",
+                "responds within 10ms: took 30ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_equals_should_fall_back_to_string() {
+        let expectation = JsonExpectation::parse_equals("$.name=ada").unwrap();
+
+        assert_eq!(expectation.equals, Some(json!("ada")));
+    }
+
+    #[test]
+    fn validate_should_reject_bad_paths() {
+        let mut expectations = Expectations {
+            json: vec![
+                JsonExpectation::exists("$.user.id"),
+                JsonExpectation::parse_equals("$.items[0].name=ada").unwrap(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(expectations.validate(), Ok(()));
+
+        expectations.json.push(JsonExpectation::exists("$.items[x"));
+        assert!(expectations.validate().is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Runs `job` over every item with at most `jobs` running at once.
+/// Results are returned in the same order as the items.
+pub fn run_batch<T, R, F>(items: Vec<T>, jobs: usize, job: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let total = items.len();
+    let queue: Vec<Mutex<Option<T>>> = items
+        .into_iter()
+        .map(|item| Mutex::new(Some(item)))
+        .collect();
+    let results: Vec<Mutex<Option<R>>> = (0..total).map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, total.max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= total {
+                    break;
+                }
+
+                let item = queue[index]
+                    .lock()
+                    .expect("batch queue was poisoned")
+                    .take()
+                    .expect("batch item was taken twice");
+                let result = job(item);
+                *results[index].lock().expect("batch results were poisoned") = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| {
+            result
+                .into_inner()
+                .expect("batch results were poisoned")
+                .expect("batch job did not finish")
+        })
+        .collect()
+}
+
+/// Matches `*` against any run of characters and `?` against a single one
+pub fn matches_glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last star swallow one more character and try again
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn run_batch_should_keep_order_and_limit_concurrency() {
+        let running = AtomicUsize::new(0);
+        let most_running = AtomicUsize::new(0);
+
+        let results = run_batch((0..12).collect(), 3, |item: u64| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most_running.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(12 - item));
+            running.fetch_sub(1, Ordering::SeqCst);
+            item * 2
+        });
+
+        assert_eq!(results, (0..12).map(|item| item * 2).collect::<Vec<u64>>());
+        assert!(most_running.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn matches_glob_should_support_wildcards() {
+        assert!(matches_glob("orders/*", "orders/create"));
+        assert!(matches_glob("*/get", "users/get"));
+        assert!(matches_glob("users/?et", "users/get"));
+        assert!(matches_glob("a*b*c", "aXXbYYbc"));
+        assert!(!matches_glob("orders/*", "users/orders/create"));
+        assert!(!matches_glob("users/?", "users/get"));
+    }
+}
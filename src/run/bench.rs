@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::result::RunResult;
+
+/// Latency, throughput and status figures for a command that was run repeatedly
+pub struct BenchReport {
+    requests: usize,
+    elapsed: Duration,
+    // Sorted so percentiles can be read straight off
+    latencies_ms: Vec<f64>,
+    statuses: BTreeMap<String, usize>,
+    errors: BTreeMap<String, usize>,
+    // Requests that errored or got an error status
+    failed: usize,
+}
+
+impl BenchReport {
+    pub fn new(results: &[Result<RunResult, String>], elapsed: Duration) -> Self {
+        let mut latencies_ms = Vec::new();
+        let mut statuses = BTreeMap::new();
+        let mut errors = BTreeMap::new();
+        let mut failed = 0;
+
+        for result in results {
+            if result
+                .as_ref()
+                .map_or(true, |result| result.failure().is_some())
+            {
+                failed += 1;
+            }
+
+            let completed = result
+                .as_ref()
+                .map_err(|message| message.to_owned())
+                .and_then(|result| match result.error_message() {
+                    Some(message) => Err(message),
+                    None => Ok(result),
+                });
+
+            match completed {
+                Ok(result) => {
+                    latencies_ms.push(BenchReport::latency_ms(result));
+
+                    let status = result
+                        .status_code()
+                        .map(|code| code.to_string())
+                        .unwrap_or_else(|| "none".to_string());
+                    *statuses.entry(status).or_insert(0) += 1;
+                }
+                Err(message) => *errors.entry(message).or_insert(0) += 1,
+            }
+        }
+
+        latencies_ms.sort_by(|a, b| a.total_cmp(b));
+
+        Self {
+            requests: results.len(),
+            elapsed,
+            latencies_ms,
+            statuses,
+            errors,
+            failed,
+        }
+    }
+
+    /// How many requests errored or got a status of 400 or above
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    // curl's own timing leaves out the time spent starting the process
+    fn latency_ms(result: &RunResult) -> f64 {
+        match result
+            .response
+            .as_ref()
+            .and_then(|response| response.metrics.as_ref())
+        {
+            Some(metrics) => metrics.time_total * 1000.0,
+            None => result.duration.as_secs_f64() * 1000.0,
+        }
+    }
+
+    /// Nearest-rank percentile of the completed requests
+    pub fn percentile(&self, percent: f64) -> Option<f64> {
+        if self.latencies_ms.is_empty() {
+            return None;
+        }
+
+        let rank = (percent / 100.0 * self.latencies_ms.len() as f64).ceil() as usize;
+        Some(self.latencies_ms[rank.clamp(1, self.latencies_ms.len()) - 1])
+    }
+
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+
+        self.requests as f64 / seconds
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        let ms = |value: Option<f64>| match value {
+            Some(value) => format!("{:.1}ms", value),
+            None => "-".to_string(),
+        };
+
+        let mut lines = vec![
+            format!(
+                "{} requests in {:.2?}, {:.1} requests/s",
+                self.requests,
+                self.elapsed,
+                self.throughput()
+            ),
+            format!(
+                "Latency  min {}  p50 {}  p90 {}  p99 {}  max {}",
+                ms(self.latencies_ms.first().copied()),
+                ms(self.percentile(50.0)),
+                ms(self.percentile(90.0)),
+                ms(self.percentile(99.0)),
+                ms(self.latencies_ms.last().copied())
+            ),
+        ];
+
+        if !self.statuses.is_empty() {
+            lines.push("Status codes".to_string());
+            for (status, count) in &self.statuses {
+                lines.push(format!("  {:>4}  {}", status, count));
+            }
+        }
+
+        if !self.errors.is_empty() {
+            lines.push("Errors".to_string());
+            for (message, count) in &self.errors {
+                lines.push(format!("  {:>4}  {}", count, message));
+            }
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::response::{ResponseMeta, Status};
+
+    fn result(code: u16, millis: u64) -> Result<RunResult, String> {
+        Ok(RunResult::new(
+            Some(0),
+            Duration::from_millis(millis),
+            Vec::new(),
+            Vec::new(),
+        )
+        .with_response(Some(ResponseMeta {
+            status: Some(Status {
+                version: "HTTP/1.1".to_string(),
+                code,
+                reason: String::new(),
+            }),
+            ..Default::default()
+        })))
+    }
+
+    #[test]
+    fn percentile_should_use_nearest_rank() {
+        let results: Vec<Result<RunResult, String>> =
+            (1..=100).map(|millis| result(200, millis)).collect();
+
+        let report = BenchReport::new(&results, Duration::from_secs(2));
+
+        assert_eq!(report.percentile(50.0), Some(50.0));
+        assert_eq!(report.percentile(90.0), Some(90.0));
+        assert_eq!(report.percentile(99.0), Some(99.0));
+        assert_eq!(report.throughput(), 50.0);
+    }
+
+    #[test]
+    fn new_should_count_statuses_and_errors() {
+        let results = vec![
+            result(200, 10),
+            result(500, 20),
+            result(200, 30),
+            Ok(RunResult::new(
+                Some(7),
+                Duration::ZERO,
+                Vec::new(),
+                Vec::new(),
+            )),
+            Err("Unable to run curl".to_string()),
+        ];
+
+        let report = BenchReport::new(&results, Duration::from_secs(1));
+
+        assert_eq!(report.statuses.get("200"), Some(&2));
+        assert_eq!(report.statuses.get("500"), Some(&1));
+        assert_eq!(report.errors.values().sum::<usize>(), 2);
+        assert_eq!(report.latencies_ms, vec![10.0, 20.0, 30.0]);
+        assert_eq!(report.failed(), 3);
+    }
+}
@@ -0,0 +1,154 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::json_path;
+use super::result::RunResult;
+
+/// Where in a response a workspace variable is read from
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSource {
+    JsonPath(String),
+    Header(String),
+    // The first group is used when the pattern has one, otherwise the whole match
+    Regex(String),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Capture {
+    pub variable: String,
+    pub source: CaptureSource,
+}
+
+impl Capture {
+    pub fn new(variable: &str, source: CaptureSource) -> Self {
+        Self {
+            variable: variable.to_string(),
+            source,
+        }
+    }
+
+    /// Checks the path or pattern can be read, so a mistake is reported when the capture
+    /// is added rather than after a request has been sent
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.source {
+            CaptureSource::JsonPath(path) => json_path::validate(path),
+            CaptureSource::Header(_) => Ok(()),
+            CaptureSource::Regex(pattern) => Regex::new(pattern)
+                .map(|_| ())
+                .map_err(|error| format!("invalid regex {}: {}", pattern, error)),
+        }
+    }
+
+    pub fn extract(&self, result: &RunResult) -> Result<String, String> {
+        match &self.source {
+            CaptureSource::JsonPath(path) => {
+                let body = serde_json::from_slice::<Value>(&result.stdout)
+                    .map_err(|error| format!("body is not JSON: {}", error))?;
+
+                match json_path::select(&body, path)? {
+                    // Strings are captured without their quotes
+                    Some(Value::String(value)) => Ok(value.to_owned()),
+                    Some(value) => Ok(value.to_string()),
+                    None => Err(format!("{} was not found in the body", path)),
+                }
+            }
+            CaptureSource::Header(name) => result
+                .response
+                .as_ref()
+                .and_then(|response| response.header(name))
+                .map(|value| value.to_string())
+                .ok_or_else(|| format!("header {} was not in the response", name)),
+            CaptureSource::Regex(pattern) => {
+                let regex = Regex::new(pattern)
+                    .map_err(|error| format!("invalid regex {}: {}", pattern, error))?;
+                let body = String::from_utf8_lossy(&result.stdout);
+
+                regex
+                    .captures(&body)
+                    .and_then(|captures| captures.get(1).or_else(|| captures.get(0)))
+                    .map(|matched| matched.as_str().to_string())
+                    .ok_or_else(|| format!("{} did not match the body", pattern))
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let source = match &self.source {
+            CaptureSource::JsonPath(path) => format!("json path {}", path),
+            CaptureSource::Header(name) => format!("header {}", name),
+            CaptureSource::Regex(pattern) => format!("regex {}", pattern),
+        };
+
+        format!("w{{{}}} from {}", self.variable, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::run::response::ResponseMeta;
+
+    fn result(body: &str) -> RunResult {
+        RunResult::new(
+            Some(0),
+            Duration::ZERO,
+            body.as_bytes().to_vec(),
+            Vec::new(),
+        )
+        .with_response(Some(ResponseMeta {
+            headers: vec![("X-Session".to_string(), "abc123".to_string())],
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn extract_should_read_json_path_without_quotes() {
+        let capture = Capture::new("token", CaptureSource::JsonPath("$.auth.token".to_string()));
+
+        let value = capture.extract(&result(r#"{ "auth": { "token": "secret" } }"#));
+
+        assert_eq!(value.unwrap(), "secret");
+    }
+
+    #[test]
+    fn extract_should_read_header() {
+        let capture = Capture::new("session", CaptureSource::Header("x-session".to_string()));
+
+        assert_eq!(capture.extract(&result("")).unwrap(), "abc123");
+    }
+
+    #[test]
+    fn extract_should_use_first_regex_group() {
+        let capture = Capture::new("id", CaptureSource::Regex(r"id=(\d+)".to_string()));
+
+        assert_eq!(capture.extract(&result("created id=42")).unwrap(), "42");
+    }
+
+    #[test]
+    fn validate_should_reject_bad_paths_and_patterns() {
+        let capture = |source| Capture::new("id", source);
+
+        assert!(
+            capture(CaptureSource::JsonPath("$.items[0].id".to_string()))
+                .validate()
+                .is_ok()
+        );
+        assert!(capture(CaptureSource::JsonPath("$.items[0".to_string()))
+            .validate()
+            .is_err());
+        assert!(capture(CaptureSource::Regex(r"id=(\d+".to_string()))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn extract_should_error_when_missing() {
+        let capture = Capture::new("token", CaptureSource::JsonPath("$.token".to_string()));
+
+        assert!(capture.extract(&result("{}")).is_err());
+    }
+}
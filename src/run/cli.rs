@@ -1,21 +1,124 @@
 use crate::common::CurldCommand;
 use crate::common::IO;
+use crate::prompt::settings::PromptManager;
 use crate::variables::builder::VariablesBuilder;
+use crate::variables::placeholder::Placeholder;
+use crate::workspaces::settings::WorkspacesManager;
+use serde_json::Value;
 use std::collections::HashMap;
+use std::io::Read;
+use std::process::ExitCode;
+use std::time::Instant;
 
-use super::settings::RunManager;
+use super::assertions::{Expectations, HeaderExpectation, JsonExpectation};
+use super::batch::run_batch;
+use super::bench::BenchReport;
+use super::captures::{Capture, CaptureSource};
+use super::history::HistoryEntry;
+use super::result::RunResult;
+use super::settings::{RunManager, SavedCommand};
+use super::stream::{stream_with_args, Sink};
 use super::utils::run_with_args;
 
+// Values haven't been read from stdin yet where this is checked, so input is only off
+// because of `--no-input`
+static REPROMPT_WITHOUT_INPUT: &str =
+    "--reprompt asks for values again, so it can't be used with --no-input";
+
 #[derive(clap::Args, Debug)]
 pub struct RunInput {
     #[arg(short, long)]
     pub id: Option<String>,
 
+    #[command(flatten)]
+    pub output: OutputInput,
+
+    #[command(flatten)]
+    pub vars: VarsInput,
+
     // This is being used so clap doesn't try to interpret the curl args
     #[arg(raw = true)]
     pub user_args: Vec<String>,
 }
 
+#[derive(clap::Args, Debug, Default)]
+pub struct OutputInput {
+    /// Forward curl's output as it arrives instead of once the request has finished
+    #[arg(short, long, default_value = "false")]
+    pub stream: bool,
+
+    /// Stream the response body into a file
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+// Values given up front instead of being prompted for
+#[derive(clap::Args, Debug, Default)]
+pub struct VarsInput {
+    /// A value for an r{} or s{} variable, as `key=value`
+    #[arg(long = "var", value_name = "KEY=VALUE")]
+    pub vars: Vec<String>,
+
+    /// Read values from a JSON object in a file
+    #[arg(long)]
+    pub vars_file: Option<String>,
+
+    /// Read values from a JSON object on stdin
+    #[arg(long, default_value = "false")]
+    pub vars_stdin: bool,
+}
+
+impl VarsInput {
+    /// The values given, with `--var` taking precedence over a file or stdin
+    pub fn values(&self) -> Result<HashMap<String, String>, String> {
+        let mut values = HashMap::new();
+
+        if let Some(path) = &self.vars_file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|error| format!("Could not read {}: {}", path, error))?;
+            let file_values = VarsInput::parse_json(&contents)
+                .map_err(|message| format!("Could not read values from {}: {}", path, message))?;
+            values.extend(file_values);
+        }
+
+        if self.vars_stdin {
+            let mut contents = String::new();
+            std::io::stdin()
+                .read_to_string(&mut contents)
+                .map_err(|error| format!("Could not read stdin: {}", error))?;
+            // Nothing is left on stdin to answer prompts with
+            IO::disable_input();
+
+            let stdin_values = VarsInput::parse_json(&contents)
+                .map_err(|message| format!("Could not read values from stdin: {}", message))?;
+            values.extend(stdin_values);
+        }
+
+        for var in &self.vars {
+            match var.split_once('=') {
+                Some((key, value)) => values.insert(key.trim().to_string(), value.to_string()),
+                None => return Err(format!("--var {} should be written as key=value", var)),
+            };
+        }
+
+        Ok(values)
+    }
+
+    // Strings are used as they are, anything else as its JSON
+    fn parse_json(contents: &str) -> Result<HashMap<String, String>, String> {
+        match serde_json::from_str(contents).map_err(|error| error.to_string())? {
+            Value::Object(object) => Ok(object
+                .into_iter()
+                .map(|(key, value)| match value {
+                    Value::String(text) => (key, text),
+                    other => (key, other.to_string()),
+                })
+                .collect()),
+            _ => Err("expected an object of names and values".to_string()),
+        }
+    }
+}
+
 #[derive(clap::Args, Debug)]
 pub struct HistoryInput {
     #[arg(short, long, default_value = "false")]
@@ -23,14 +126,119 @@ pub struct HistoryInput {
 
     #[arg(short, long)]
     pub run: Option<usize>,
+
+    /// Ask for the values of the entry being run again, offering the ones it used as defaults
+    #[arg(long, default_value = "false", requires = "run")]
+    pub reprompt: bool,
+
+    /// Print the response stored with a history entry without sending the request again
+    #[arg(short, long)]
+    pub show: Option<usize>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExpectInput {
+    /// Id of the saved command
+    pub id: String,
+
+    #[arg(long)]
+    pub status: Option<u16>,
+
+    /// A header the response must have, optionally with its value as `name: value`
+    #[arg(long = "header")]
+    pub headers: Vec<String>,
+
+    /// A JSON path that must equal a value, as `path=value`
+    #[arg(long)]
+    pub json: Vec<String>,
+
+    /// A JSON path that must exist
+    #[arg(long)]
+    pub json_exists: Vec<String>,
+
+    /// Maximum time the request may take in milliseconds
+    #[arg(long)]
+    pub max_latency: Option<u64>,
+
+    /// Remove all expectations from the saved command
+    #[arg(long, default_value = "false")]
+    pub clear: bool,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("source").multiple(false)))]
+pub struct CaptureInput {
+    /// Id of the saved command
+    pub id: String,
+
+    /// Workspace variable the captured value is written to
+    #[arg(requires = "source")]
+    pub variable: Option<String>,
+
+    /// Capture the value at a JSON path in the body, like $.auth.token
+    #[arg(long, group = "source")]
+    pub json_path: Option<String>,
+
+    /// Capture the value of a response header
+    #[arg(long, group = "source")]
+    pub header: Option<String>,
+
+    /// Capture the first group of a regex matched against the body
+    #[arg(long, group = "source")]
+    pub regex: Option<String>,
+
+    /// Remove all captures from the saved command
+    #[arg(long, default_value = "false", conflicts_with = "variable")]
+    pub clear: bool,
 }
 
 #[derive(clap::Subcommand, Debug)]
 pub enum RunCommand {
     Run(RunInput),
     History(HistoryInput),
-    RunSaved { id: String },
+    /// Run saved commands, several ids or globs like `orders/*` run concurrently
+    RunSaved {
+        #[arg(required = true)]
+        ids: Vec<String>,
+
+        /// How many commands may run at the same time
+        #[arg(short, long, default_value = "4")]
+        jobs: usize,
+
+        #[command(flatten)]
+        output: OutputInput,
+
+        #[command(flatten)]
+        vars: VarsInput,
+
+        /// Ask for every value again, offering the last ones used as defaults
+        #[arg(long, default_value = "false")]
+        reprompt: bool,
+    },
     List,
+    /// Attach expectations to a saved command, or show them when none are given
+    Expect(ExpectInput),
+    /// Write values from a saved command's response into the current workspace, or list them
+    Capture(CaptureInput),
+    /// Run a saved command repeatedly and report its latency and status codes
+    Bench {
+        id: String,
+
+        /// How many requests to send
+        #[arg(short = 'n', long, default_value = "100")]
+        requests: usize,
+
+        /// How many requests may be in flight at the same time
+        #[arg(short, long, default_value = "10")]
+        concurrency: usize,
+
+        #[command(flatten)]
+        vars: VarsInput,
+    },
+    /// Run saved commands and check their expectations, all with expectations when no ids are given
+    Test {
+        ids: Vec<String>,
+    },
 }
 
 impl RunCommand {
@@ -38,81 +246,882 @@ impl RunCommand {
         run_cmd: &RunCommand,
         run_settings: &mut RunManager,
         variables_builder: &mut VariablesBuilder,
-    ) {
+        workspaces_manager: &mut WorkspacesManager,
+        prompt_manager: &mut PromptManager,
+    ) -> ExitCode {
         match run_cmd {
             RunCommand::Run(input) => {
-                let RunInput { user_args, id } = input;
-
-                let extracted_keys = variables_builder.extract(user_args);
+                let RunInput {
+                    user_args,
+                    id,
+                    output,
+                    vars,
+                } = input;
 
-                let user_values = RunCommand::prompt_for_variables(&extracted_keys);
-
-                let curld_cmd = CurldCommand::new(user_args.to_owned(), user_values);
+                let command = CurldCommand::new(user_args.to_owned(), HashMap::new());
+                let remembered = id
+                    .as_ref()
+                    .and_then(|id| run_settings.get_saved(id))
+                    .map(|saved| saved.last_values.clone())
+                    .unwrap_or_default();
+                let curld_cmd = match vars.values().and_then(|given| {
+                    RunCommand::fill_values(
+                        &command,
+                        &given,
+                        &remembered,
+                        false,
+                        variables_builder,
+                        prompt_manager,
+                    )
+                }) {
+                    Ok(command) => command,
+                    Err(message) => {
+                        IO::error(&message);
+                        return ExitCode::FAILURE;
+                    }
+                };
 
                 let runnable_cmd = variables_builder.insert(&curld_cmd);
-                let cmd_output = run_with_args(runnable_cmd);
+                let result = RunCommand::run(runnable_cmd, output);
 
                 if let Some(id) = id {
                     run_settings.add_saved(id.to_owned(), curld_cmd.to_owned());
+                    run_settings.remember_values(id, &curld_cmd.value_map);
+
+                    // Saving over a command keeps its captures, so they apply here too
+                    if let (Some(saved), Ok(result)) = (run_settings.get_saved(id), &result) {
+                        RunCommand::apply_captures(
+                            saved,
+                            result,
+                            workspaces_manager,
+                            variables_builder,
+                        );
+                    }
                 }
 
-                run_settings.insert_history(curld_cmd);
-                IO::output(&cmd_output)
+                run_settings.insert_history(HistoryEntry::new(curld_cmd, result.as_ref().ok()));
+                RunCommand::exit_code(&result)
             }
-            RunCommand::RunSaved { id } => {
-                let curld_cmd = run_settings
-                    .get_saved(id)
-                    .expect("Could not find saved command");
+            RunCommand::RunSaved {
+                ids,
+                jobs,
+                output,
+                vars,
+                reprompt,
+            } => {
+                if *reprompt && !IO::accepts_input() {
+                    IO::error(REPROMPT_WITHOUT_INPUT);
+                    return ExitCode::FAILURE;
+                }
 
-                variables_builder.extract(&curld_cmd.user_args);
+                let ids = match run_settings.find_saved(ids) {
+                    Ok(ids) => ids,
+                    Err(message) => {
+                        IO::error(&message);
+                        return ExitCode::FAILURE;
+                    }
+                };
+
+                if ids.len() > 1 && (output.stream || output.output.is_some()) {
+                    IO::error("--stream and --output can only be used with a single saved command");
+                    return ExitCode::FAILURE;
+                }
 
-                let curl_output = run_with_args(variables_builder.insert(curld_cmd));
+                let mut filled = match RunCommand::fill_saved(
+                    &ids,
+                    vars,
+                    *reprompt,
+                    run_settings,
+                    variables_builder,
+                    prompt_manager,
+                ) {
+                    Ok(filled) => filled,
+                    Err(message) => {
+                        IO::error(&message);
+                        return ExitCode::FAILURE;
+                    }
+                };
 
-                IO::output(&curl_output)
+                if ids.len() > 1 {
+                    return RunCommand::run_saved_batch(
+                        &ids,
+                        filled,
+                        *jobs,
+                        run_settings,
+                        variables_builder,
+                        workspaces_manager,
+                    );
+                }
+
+                let (saved, command) = filled.remove(0);
+                let result = RunCommand::run(variables_builder.insert(&command), output);
+                if let Ok(result) = &result {
+                    RunCommand::apply_captures(
+                        &saved,
+                        result,
+                        workspaces_manager,
+                        variables_builder,
+                    );
+                }
+
+                run_settings.insert_history(HistoryEntry::new(command, result.as_ref().ok()));
+                RunCommand::exit_code(&result)
+            }
+            RunCommand::Expect(input) => RunCommand::expect(input, run_settings),
+            RunCommand::Capture(input) => RunCommand::capture(input, run_settings),
+            RunCommand::Bench {
+                id,
+                requests,
+                concurrency,
+                vars,
+            } => RunCommand::bench(
+                id,
+                *requests,
+                *concurrency,
+                vars,
+                run_settings,
+                variables_builder,
+                prompt_manager,
+            ),
+            RunCommand::Test { ids } => {
+                RunCommand::test(ids, run_settings, variables_builder, workspaces_manager)
             }
             RunCommand::List => {
                 for id in run_settings.get_saved_keys() {
                     IO::output(&id);
                 }
+                ExitCode::SUCCESS
             }
             RunCommand::History(input) => {
+                if input.reprompt && !IO::accepts_input() {
+                    IO::error(REPROMPT_WITHOUT_INPUT);
+                    return ExitCode::FAILURE;
+                }
+
+                let mut exit_code = ExitCode::SUCCESS;
                 if let Some(index) = input.run {
                     let cmd = run_settings.get_history_entry(index);
                     match cmd {
-                        Some(args) => {
-                            let output = run_with_args(variables_builder.insert(args));
-                            IO::output(&output);
+                        Some(entry) => {
+                            let command = match RunCommand::fill_values(
+                                &entry.command,
+                                &HashMap::new(),
+                                &HashMap::new(),
+                                input.reprompt,
+                                variables_builder,
+                                prompt_manager,
+                            ) {
+                                Ok(command) => command,
+                                Err(message) => {
+                                    IO::error(&message);
+                                    return ExitCode::FAILURE;
+                                }
+                            };
+
+                            exit_code = RunCommand::exit_code(&RunCommand::run(
+                                variables_builder.insert(&command),
+                                &OutputInput::default(),
+                            ));
                         }
                         None => IO::output(&index.to_string()),
                     }
                 }
 
+                if let Some(index) = input.show {
+                    match run_settings.get_history_entry(index) {
+                        Some(entry) => {
+                            IO::output(&variables_builder.to_string(&entry.command));
+                            match &entry.response {
+                                Some(response) => IO::output(&response.to_lines().join("\n")),
+                                None => IO::output("No response was stored for this entry"),
+                            }
+                        }
+                        None => {
+                            IO::error(&format!("No history entry at {}", index));
+                            exit_code = ExitCode::FAILURE;
+                        }
+                    }
+                }
+
                 if input.list {
                     for history in run_settings.get_history_entries(variables_builder) {
                         IO::output(&history);
                     }
                 }
+                exit_code
+            }
+        }
+    }
+
+    fn expect(input: &ExpectInput, run_settings: &mut RunManager) -> ExitCode {
+        let no_expectations = input.status.is_none()
+            && input.headers.is_empty()
+            && input.json.is_empty()
+            && input.json_exists.is_empty()
+            && input.max_latency.is_none();
+
+        if no_expectations && !input.clear {
+            return match run_settings.get_saved(&input.id) {
+                Some(saved) => {
+                    IO::output(
+                        &serde_json::to_string_pretty(&saved.expect)
+                            .expect("Unable to serialize expectations"),
+                    );
+                    ExitCode::SUCCESS
+                }
+                None => {
+                    IO::error(&format!("Could not find saved command {}", input.id));
+                    ExitCode::FAILURE
+                }
+            };
+        }
+
+        let json = input
+            .json
+            .iter()
+            .map(|expectation| JsonExpectation::parse_equals(expectation))
+            .collect::<Result<Vec<JsonExpectation>, String>>();
+
+        let expect = json.map(|json| Expectations {
+            status: input.status,
+            headers: input
+                .headers
+                .iter()
+                .map(|header| HeaderExpectation::parse(header))
+                .collect(),
+            json: json
+                .into_iter()
+                .chain(
+                    input
+                        .json_exists
+                        .iter()
+                        .map(|path| JsonExpectation::exists(path)),
+                )
+                .collect(),
+            max_latency_ms: input.max_latency,
+        });
+
+        let set = expect.and_then(|expect| {
+            expect.validate()?;
+            run_settings.set_expectations(&input.id, expect)
+        });
+        match set {
+            Ok(()) => {
+                IO::output(&format!("Expectations set for {}", input.id));
+                ExitCode::SUCCESS
+            }
+            Err(message) => {
+                IO::error(&message);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    fn capture(input: &CaptureInput, run_settings: &mut RunManager) -> ExitCode {
+        let source = match (&input.json_path, &input.header, &input.regex) {
+            (Some(path), _, _) => Some(CaptureSource::JsonPath(path.to_owned())),
+            (_, Some(name), _) => Some(CaptureSource::Header(name.to_owned())),
+            (_, _, Some(pattern)) => Some(CaptureSource::Regex(pattern.to_owned())),
+            _ => None,
+        };
+
+        let updated = match (&input.variable, source) {
+            (Some(variable), Some(source)) => {
+                let capture = Capture::new(variable, source);
+                let description = capture.describe();
+                capture
+                    .validate()
+                    .and_then(|_| run_settings.add_capture(&input.id, capture))
+                    .map(|_| format!("Capture added: {}", description))
+            }
+            _ if input.clear => run_settings
+                .clear_captures(&input.id)
+                .map(|_| format!("Captures removed from {}", input.id)),
+            _ => match run_settings.get_saved(&input.id) {
+                Some(saved) => Ok(saved
+                    .captures
+                    .iter()
+                    .map(|capture| capture.describe())
+                    .collect::<Vec<String>>()
+                    .join("\n")),
+                None => Err(format!("Could not find saved command {}", input.id)),
+            },
+        };
+
+        match updated {
+            Ok(message) => {
+                IO::output(&message);
+                ExitCode::SUCCESS
+            }
+            Err(message) => {
+                IO::error(&message);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    /// Fills in the values of saved commands, remembering them for the next time they are asked for
+    fn fill_saved(
+        ids: &[String],
+        vars: &VarsInput,
+        reprompt: bool,
+        run_settings: &mut RunManager,
+        variables_builder: &mut VariablesBuilder,
+        prompt_manager: &mut PromptManager,
+    ) -> Result<Vec<(SavedCommand, CurldCommand)>, String> {
+        let given = vars.values()?;
+
+        let mut filled = Vec::new();
+        for id in ids {
+            let saved = run_settings
+                .get_saved(id)
+                .ok_or_else(|| format!("Could not find saved command {}", id))?
+                .to_owned();
+            let command = RunCommand::fill_values(
+                &saved.command,
+                &given,
+                &saved.last_values,
+                reprompt,
+                variables_builder,
+                prompt_manager,
+            )?;
+
+            // Values taken from the saved command weren't entered this time
+            let entered: HashMap<String, String> = command
+                .value_map
+                .iter()
+                .filter(|(key, _)| reprompt || !saved.command.value_map.contains_key(*key))
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect();
+            run_settings.remember_values(id, &entered);
+            filled.push((saved, command));
+        }
+
+        Ok(filled)
+    }
+
+    /// Runs several saved commands at once, printing their outputs in the order they
+    /// were asked for followed by a summary
+    fn run_saved_batch(
+        ids: &[String],
+        filled: Vec<(SavedCommand, CurldCommand)>,
+        jobs: usize,
+        run_settings: &mut RunManager,
+        variables_builder: &mut VariablesBuilder,
+        workspaces_manager: &mut WorkspacesManager,
+    ) -> ExitCode {
+        // The builder isn't shared across threads, so arguments are resolved up front
+        let (saved, commands): (Vec<SavedCommand>, Vec<CurldCommand>) = filled.into_iter().unzip();
+        let args = commands
+            .iter()
+            .map(|command| variables_builder.insert(command))
+            .collect();
+
+        let results = run_batch(args, jobs, run_with_args);
+
+        let mut failed = 0;
+        let mut rows = Vec::new();
+        let width = ids.iter().map(|id| id.len()).max().unwrap_or(0);
+        // A body piped without a new line at the end would run into the next header
+        let mut ends_line = true;
+        for (((id, saved), command), result) in ids.iter().zip(saved).zip(commands).zip(results) {
+            match ends_line {
+                true => IO::output(&format!("==> {} <==", id)),
+                false => IO::output(&format!("\n==> {} <==", id)),
+            }
+            ends_line = true;
+
+            let row = match &result {
+                Ok(result) => {
+                    RunCommand::display(result);
+                    if !IO::is_terminal() {
+                        ends_line = RunCommand::ends_line(&result.stdout);
+                    }
+                    RunCommand::apply_captures(
+                        &saved,
+                        result,
+                        workspaces_manager,
+                        variables_builder,
+                    );
+
+                    match result.failure() {
+                        Some(message) => {
+                            failed += 1;
+                            format!(
+                                "  FAIL  {:width$}  {}  {}",
+                                id,
+                                result.status_row(),
+                                message
+                            )
+                        }
+                        None => format!("  ok    {:width$}  {}", id, result.status_row()),
+                    }
+                }
+                Err(message) => {
+                    IO::error(message);
+                    failed += 1;
+                    format!("  FAIL  {:width$}  {}", id, message)
+                }
+            };
+            rows.push(row);
+
+            run_settings.insert_history(HistoryEntry::new(command, result.as_ref().ok()));
+        }
+
+        IO::output(&format!(
+            "\n{} commands: {} succeeded, {} failed",
+            ids.len(),
+            ids.len() - failed,
+            failed
+        ));
+        for row in rows {
+            IO::output(&row);
+        }
+
+        if failed > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+
+    // Whether the next output starts on a line of its own after this was written
+    fn ends_line(output: &[u8]) -> bool {
+        output.last().is_none_or(|last| *last == b'\n')
+    }
+
+    pub fn apply_captures(
+        saved: &SavedCommand,
+        result: &RunResult,
+        workspaces_manager: &mut WorkspacesManager,
+        variables_builder: &VariablesBuilder,
+    ) {
+        for capture in &saved.captures {
+            // The response decides what is captured, so it is kept from reading other values
+            let captured = capture.extract(result).and_then(|value| {
+                workspaces_manager
+                    .set_variable(&capture.variable, &variables_builder.escape(&value))
+            });
+            match captured {
+                Ok(()) => IO::info(&format!("Captured w{{{}}}", capture.variable)),
+                Err(message) => IO::error(&format!(
+                    "Unable to capture w{{{}}}: {}",
+                    capture.variable, message
+                )),
+            }
+        }
+    }
+
+    fn bench(
+        id: &String,
+        requests: usize,
+        concurrency: usize,
+        vars: &VarsInput,
+        run_settings: &mut RunManager,
+        variables_builder: &mut VariablesBuilder,
+        prompt_manager: &mut PromptManager,
+    ) -> ExitCode {
+        // Filled in once, like run-saved, so every request sends exactly the same thing
+        let filled = RunCommand::fill_saved(
+            &[id.to_owned()],
+            vars,
+            false,
+            run_settings,
+            variables_builder,
+            prompt_manager,
+        );
+        let command = match filled {
+            Ok(mut filled) => filled.remove(0).1,
+            Err(message) => {
+                IO::error(&message);
+                return ExitCode::FAILURE;
+            }
+        };
+        let args = variables_builder.insert(&command);
+
+        IO::info(&format!(
+            "Sending {} requests to {}, {} at a time",
+            requests, id, concurrency
+        ));
+
+        let started = Instant::now();
+        let results = run_batch(vec![args; requests], concurrency, run_with_args);
+        let report = BenchReport::new(&results, started.elapsed());
+
+        IO::output(&report.to_lines().join("\n"));
+        // Fails like the other ways of running a command when any request did
+        match report.failed() {
+            0 => ExitCode::SUCCESS,
+            _ => ExitCode::FAILURE,
+        }
+    }
+
+    fn test(
+        ids: &[String],
+        run_settings: &RunManager,
+        variables_builder: &mut VariablesBuilder,
+        workspaces_manager: &mut WorkspacesManager,
+    ) -> ExitCode {
+        let mut ids = ids.to_vec();
+        if ids.is_empty() {
+            ids = run_settings
+                .get_saved_keys()
+                .into_iter()
+                .filter(|id| {
+                    run_settings
+                        .get_saved(id)
+                        .is_some_and(|saved| !saved.expect.is_empty())
+                })
+                .collect();
+            ids.sort();
+        }
+
+        let mut failed = 0;
+        for id in &ids {
+            let saved = match run_settings.get_saved(id) {
+                Some(saved) => saved,
+                None => {
+                    IO::output(&format!("FAIL {}", id));
+                    IO::output("  x saved command exists: not found");
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            if let Err(error) = variables_builder.extract(&saved.command.user_args) {
+                IO::output(&format!("FAIL {}", id));
+                IO::output(&format!("  x template is valid: {}", error.message()));
+                failed += 1;
+                continue;
+            }
+            if let Err(message) = variables_builder.fill(&HashMap::new()) {
+                IO::output(&format!("FAIL {}", id));
+                IO::output(&format!("  x secrets are given: {}", message));
+                failed += 1;
+                continue;
+            }
+
+            let outcomes = match run_with_args(variables_builder.insert(&saved.command)) {
+                Ok(result) => {
+                    RunCommand::apply_captures(
+                        saved,
+                        &result,
+                        workspaces_manager,
+                        variables_builder,
+                    );
+                    saved.expect.check(&result)
+                }
+                Err(message) => {
+                    IO::output(&format!("FAIL {}", id));
+                    IO::output(&format!("  x request completed: {}", message));
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            let passed = outcomes.iter().all(|outcome| outcome.passed());
+            IO::output(&format!("{} {}", if passed { "PASS" } else { "FAIL" }, id));
+            for outcome in outcomes {
+                match outcome.failure {
+                    Some(failure) => {
+                        IO::output(&format!("  x {}: {}", outcome.description, failure))
+                    }
+                    None => IO::output(&format!("  ok {}", outcome.description)),
+                }
+            }
+
+            if !passed {
+                failed += 1;
             }
         }
+
+        IO::output(&format!(
+            "\n{} passed, {} failed",
+            ids.len() - failed,
+            failed
+        ));
+
+        if failed > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+
+    pub fn run(args: Vec<String>, output: &OutputInput) -> Result<RunResult, String> {
+        let result = if output.stream || output.output.is_some() {
+            stream_with_args(args, &Sink::new(&output.output))
+        } else {
+            run_with_args(args).inspect(RunCommand::display)
+        };
+
+        if let Some(summary) = result
+            .as_ref()
+            .ok()
+            .and_then(|result| result.response.as_ref())
+            .and_then(|response| response.summary_line())
+        {
+            IO::info(&summary);
+        }
+
+        result
     }
 
-    fn prompt_for_variables(template_keys: &Vec<String>) -> HashMap<String, String> {
-        let mut template_map: HashMap<String, String> = HashMap::new();
-        RunCommand::loop_prompt(template_keys, &mut template_map);
+    fn exit_code(result: &Result<RunResult, String>) -> ExitCode {
+        let result = match result {
+            Ok(result) => result,
+            Err(message) => {
+                IO::error(message);
+                return ExitCode::FAILURE;
+            }
+        };
 
-        template_map
+        // Error statuses fail the run like they do in batches and flows, and scripts can
+        // tell curl failures apart using the same exit codes curl uses
+        match (result.failure_exit_code(), result.failure()) {
+            (Some(code), Some(message)) => {
+                IO::error(&format!("{} ({:.2?})", message, result.duration));
+                ExitCode::from(code)
+            }
+            _ => ExitCode::SUCCESS,
+        }
     }
 
-    fn loop_prompt(keys: &Vec<String>, map: &mut HashMap<String, String>) {
-        for key in keys {
-            if map.contains_key(key) {
+    fn display(result: &RunResult) {
+        if !IO::is_terminal() {
+            // Piped output is the body untouched so binary bodies survive, and anything curl
+            // printed besides it, like errors or its progress meter, stays out of it
+            IO::output_bytes(&result.stdout);
+            if !result.stderr.is_empty() {
+                IO::error(String::from_utf8_lossy(&result.stderr).trim_end());
+            }
+        } else if result.is_binary() {
+            IO::output(&result.summary());
+        } else {
+            IO::output(&String::from_utf8_lossy(result.output()));
+        }
+    }
+
+    /// Fills in a command's values from the ones given up front, then prompts for the rest.
+    /// With `reprompt` the stored values are asked for again, offered as defaults along with
+    /// the ones remembered from earlier runs.
+    fn fill_values(
+        command: &CurldCommand,
+        given: &HashMap<String, String>,
+        remembered: &HashMap<String, String>,
+        reprompt: bool,
+        variables_builder: &mut VariablesBuilder,
+        prompt_manager: &mut PromptManager,
+    ) -> Result<CurldCommand, String> {
+        let mut placeholders = variables_builder
+            .extract(&command.user_args)
+            .map_err(|error| error.to_string())?;
+
+        // Piped input answers the prompts in order, so nothing is offered in its place
+        if IO::is_interactive() {
+            let mut previous = command.value_map.clone();
+            previous.extend(remembered.clone());
+            placeholders = placeholders
+                .into_iter()
+                .map(|placeholder| {
+                    let value = previous.get(&placeholder.name);
+                    placeholder.remembering(value)
+                })
+                .collect();
+        }
+
+        // Only values the command uses are kept with it
+        let mut value_map = match reprompt {
+            true => HashMap::new(),
+            false => command.value_map.clone(),
+        };
+        value_map.extend(
+            given
+                .iter()
+                .filter(|(key, _)| {
+                    placeholders
+                        .iter()
+                        .any(|placeholder| &placeholder.name == *key)
+                })
+                .map(|(key, value)| (key.to_owned(), value.to_owned())),
+        );
+        RunCommand::loop_prompt(&placeholders, &mut value_map, prompt_manager)?;
+        variables_builder.fill(given)?;
+
+        Ok(CurldCommand::new(command.user_args.to_owned(), value_map))
+    }
+
+    pub fn loop_prompt(
+        placeholders: &[Placeholder],
+        map: &mut HashMap<String, String>,
+        prompt_manager: &mut PromptManager,
+    ) -> Result<(), String> {
+        if !IO::accepts_input() {
+            let missing: Vec<Placeholder> = placeholders
+                .iter()
+                .filter(|placeholder| {
+                    !map.contains_key(&placeholder.name) && placeholder.default.is_none()
+                })
+                .cloned()
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!(
+                    "Missing values for {}, give them with --var, --vars-file or --vars-stdin",
+                    Placeholder::names(&missing)
+                ));
+            }
+        }
+
+        for placeholder in placeholders {
+            if let Some(value) = map.get(&placeholder.name) {
+                placeholder.validate(value)?;
                 continue;
             }
-            let value = IO::prompt(&format!("Enter value for {}: ", key));
-            map.insert(key.to_owned(), value);
+
+            let value = if IO::is_interactive() {
+                RunCommand::prompt_until_valid(placeholder, prompt_manager)?
+            } else {
+                // Nobody is there to answer, so defaults are taken as they are and
+                // an invalid value stops the run
+                let value = match &placeholder.default {
+                    Some(default) => default.to_owned(),
+                    None => IO::prompt(&placeholder.prompt()),
+                };
+                placeholder.validate(&value)?;
+                value
+            };
+            map.insert(placeholder.name.to_owned(), value);
+        }
+
+        Ok(())
+    }
+
+    fn prompt_until_valid(
+        placeholder: &Placeholder,
+        prompt_manager: &mut PromptManager,
+    ) -> Result<String, String> {
+        if !placeholder.choices.is_empty() {
+            let message = format!("Choose a value for {}", placeholder.name);
+            let selected = placeholder
+                .choices
+                .iter()
+                .position(|choice| Some(choice) == placeholder.default.as_ref())
+                .unwrap_or(0);
+            return IO::select(&message, &placeholder.choices, selected);
+        }
+
+        // The default starts out on the line so it can be edited
+        let initial = placeholder.default.clone().unwrap_or_default();
+        loop {
+            let value = placeholder.answer(prompt_manager.read(
+                &placeholder.name,
+                &placeholder.prompt(),
+                &initial,
+            ));
+            match placeholder.validate(&value) {
+                Ok(()) => {
+                    prompt_manager.add_history(&placeholder.name, &value);
+                    return Ok(value);
+                }
+                Err(message) => IO::error(&message),
+            }
         }
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ends_line_should_only_be_false_after_an_unfinished_line() {
+        assert!(RunCommand::ends_line(b""));
+        assert!(RunCommand::ends_line(b"{\"id\": 1}\n"));
+        assert!(!RunCommand::ends_line(b"{\"id\": 1}"));
+    }
+
+    #[test]
+    fn vars_should_take_precedence_over_file() {
+        let path = std::env::temp_dir().join("curld_vars_test.json");
+        std::fs::write(&path, r#"{"id": 7, "name": "ada", "tags": ["a"]}"#).unwrap();
+
+        let vars = VarsInput {
+            vars: vec!["name=grace".to_string(), "query=a=b".to_string()],
+            vars_file: Some(path.to_string_lossy().to_string()),
+            vars_stdin: false,
+        };
+        let values = vars.values().unwrap();
+
+        assert_eq!(values.get("id"), Some(&"7".to_string()));
+        assert_eq!(values.get("name"), Some(&"grace".to_string()));
+        assert_eq!(values.get("tags"), Some(&r#"["a"]"#.to_string()));
+        assert_eq!(values.get("query"), Some(&"a=b".to_string()));
+    }
+
+    #[test]
+    fn vars_should_reject_bad_input() {
+        let vars = VarsInput {
+            vars: vec!["name".to_string()],
+            ..Default::default()
+        };
+
+        assert!(vars.values().is_err());
+        assert!(VarsInput::parse_json("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn captured_values_should_never_be_read_as_placeholders() {
+        use crate::{
+            env::mutators::EnvMutator,
+            run::{
+                captures::{Capture, CaptureSource},
+                mutators::RunMutators,
+            },
+            settings::traits::MockStoredSettings,
+            workspaces::settings::WorkspaceSettings,
+        };
+        use std::cell::RefCell;
+        use std::time::Duration;
+
+        std::env::set_var("CURLD_CAPTURE_TEST_SECRET", "sk_live_123");
+        let stored_settings = RefCell::new(MockStoredSettings::<WorkspaceSettings>::new());
+        stored_settings
+            .borrow_mut()
+            .expect_get_module()
+            .returning(|_| None);
+        stored_settings
+            .borrow_mut()
+            .expect_insert_module()
+            .returning(|_, _| ());
+        let mut workspaces_manager = WorkspacesManager::new(&stored_settings);
+
+        let workspace_mutator = workspaces_manager.get_workspace_mutator();
+        let run_mutators = RunMutators {};
+        let env_mutator = EnvMutator {};
+        let mut builder = VariablesBuilder::new();
+        builder.add_inserter(&workspace_mutator);
+        builder.add_inserter(&run_mutators);
+        builder.add_inserter(&env_mutator);
+
+        let saved = SavedCommand {
+            captures: vec![Capture::new(
+                "token",
+                CaptureSource::JsonPath("$.token".to_string()),
+            )],
+            ..Default::default()
+        };
+        let result = RunResult::new(
+            Some(0),
+            Duration::ZERO,
+            br#"{"token": "e{CURLD_CAPTURE_TEST_SECRET} w{{x} r{y}"}"#.to_vec(),
+            Vec::new(),
+        );
+        RunCommand::apply_captures(&saved, &result, &mut workspaces_manager, &builder);
+
+        let command = CurldCommand::new(vec!["t=w{token}".to_string()], HashMap::new());
+        assert_eq!(
+            builder.insert(&command),
+            vec!["t=e{CURLD_CAPTURE_TEST_SECRET} w{{x} r{y}".to_string()]
+        );
+    }
+}
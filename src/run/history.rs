@@ -0,0 +1,154 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::common::CurldCommand;
+
+use super::response::Status;
+use super::result::RunResult;
+
+// Responses are kept in the settings file, so only the start of each body is stored
+pub static HISTORY_BODY_LIMIT: usize = 16 * 1024;
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct HistoryEntry {
+    // Flattened so entries saved before responses were stored still load
+    #[serde(flatten)]
+    pub command: CurldCommand,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<StoredResponse>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct StoredResponse {
+    pub timestamp: DateTime<Utc>,
+
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+
+    #[serde(default)]
+    pub duration_ms: u128,
+
+    #[serde(default)]
+    pub status: Option<Status>,
+
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+
+    #[serde(default)]
+    pub body: String,
+
+    #[serde(default)]
+    pub body_size: usize,
+
+    #[serde(default)]
+    pub truncated: bool,
+
+    #[serde(default)]
+    pub binary: bool,
+}
+
+impl HistoryEntry {
+    pub fn new(command: CurldCommand, result: Option<&RunResult>) -> Self {
+        Self {
+            command,
+            response: result.map(StoredResponse::new),
+        }
+    }
+}
+
+impl StoredResponse {
+    pub fn new(result: &RunResult) -> Self {
+        let output = result.output();
+        let binary = result.is_binary();
+        let kept = &output[..output.len().min(HISTORY_BODY_LIMIT)];
+
+        let (status, headers) = match &result.response {
+            Some(response) => (response.status.clone(), response.headers.clone()),
+            None => (None, Vec::new()),
+        };
+
+        Self {
+            timestamp: Utc::now(),
+            exit_code: result.exit_code,
+            duration_ms: result.duration.as_millis(),
+            status,
+            headers,
+            body: if binary {
+                result.summary()
+            } else {
+                String::from_utf8_lossy(kept).to_string()
+            },
+            body_size: output.len(),
+            truncated: !binary && kept.len() < output.len(),
+            binary,
+        }
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Received {timestamp} in {duration}ms, curl exit code {exit_code}",
+            timestamp = self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            duration = self.duration_ms,
+            exit_code = self
+                .exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        )];
+
+        if let Some(status) = &self.status {
+            lines.push(
+                format!("{} {} {}", status.version, status.code, status.reason)
+                    .trim_end()
+                    .to_string(),
+            );
+        }
+
+        for (key, value) in &self.headers {
+            lines.push(format!("{}: {}", key, value));
+        }
+
+        lines.push(String::new());
+        lines.push(self.body.to_owned());
+
+        if self.truncated {
+            lines.push(format!(
+                "... truncated, showing {} of {} bytes",
+                HISTORY_BODY_LIMIT, self.body_size
+            ));
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn history_entry_should_load_entries_without_response() {
+        let stored = r#"{ "value_map": { "id": "1" }, "user_args": ["r{id}"] }"#;
+
+        let entry: HistoryEntry = serde_json::from_str(stored).unwrap();
+
+        assert_eq!(entry.command.user_args, vec!["r{id}".to_string()]);
+        assert!(entry.response.is_none());
+    }
+
+    #[test]
+    fn stored_response_should_cap_body() {
+        let body = vec![b'a'; HISTORY_BODY_LIMIT + 1];
+        let result = RunResult::new(Some(0), Duration::from_millis(42), body, Vec::new());
+
+        let entry = HistoryEntry::new(CurldCommand::new(vec![], HashMap::new()), Some(&result));
+        let response = entry.response.unwrap();
+
+        assert_eq!(response.body.len(), HISTORY_BODY_LIMIT);
+        assert_eq!(response.body_size, HISTORY_BODY_LIMIT + 1);
+        assert_eq!(response.duration_ms, 42);
+        assert!(response.truncated);
+    }
+}
@@ -0,0 +1,123 @@
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Looks up a value with a simple path such as `$.data.items[0].id`.
+/// The leading `$` is optional and keys containing dots can be quoted, like `$["a.b"]`.
+pub fn select<'a>(value: &'a Value, path: &str) -> Result<Option<&'a Value>, String> {
+    let segments = parse(path)?;
+
+    Ok(segments
+        .iter()
+        .try_fold(value, |current, segment| match segment {
+            Segment::Key(key) => current.get(key),
+            Segment::Index(index) => current.get(index),
+        }))
+}
+
+/// Checks a path can be read, before any body is there to look it up in
+pub fn validate(path: &str) -> Result<(), String> {
+    parse(path).map(|_| ())
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, String> {
+    let invalid = |reason: &str| format!("Invalid JSON path `{}`: {}", path, reason);
+
+    let trimmed = path.trim();
+    let trimmed = trimmed.strip_prefix('$').unwrap_or(trimmed);
+    // Allow the first key without a leading dot, like `data.id`
+    let normalised = match trimmed.chars().next() {
+        Some('.') | Some('[') | None => trimmed.to_string(),
+        Some(_) => format!(".{}", trimmed),
+    };
+
+    let mut segments = Vec::new();
+    let mut rest = normalised.as_str();
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            if end == 0 {
+                return Err(invalid("expected a key after `.`"));
+            }
+
+            segments.push(Segment::Key(after_dot[..end].to_string()));
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket
+                .find(']')
+                .ok_or_else(|| invalid("missing closing `]`"))?;
+            let inner = after_bracket[..end].trim();
+
+            let segment = if let Some(quoted) = inner
+                .strip_prefix('"')
+                .and_then(|inner| inner.strip_suffix('"'))
+            {
+                Segment::Key(quoted.to_string())
+            } else {
+                Segment::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| invalid("expected an index or a quoted key inside `[]`"))?,
+                )
+            };
+
+            segments.push(segment);
+            rest = &after_bracket[end + 1..];
+        } else {
+            return Err(invalid("expected `.` or `[`"));
+        }
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn select_should_follow_keys_and_indexes() {
+        let value = json!({ "data": { "items": [{ "id": 1 }, { "id": 2 }] } });
+
+        assert_eq!(
+            select(&value, "$.data.items[1].id").unwrap(),
+            Some(&json!(2))
+        );
+        assert_eq!(select(&value, "data.items[0].id").unwrap(), Some(&json!(1)));
+    }
+
+    #[test]
+    fn select_should_support_quoted_keys() {
+        let value = json!({ "a.b": { "c": true } });
+
+        assert_eq!(select(&value, r#"$["a.b"].c"#).unwrap(), Some(&json!(true)));
+    }
+
+    #[test]
+    fn select_should_return_none_when_missing() {
+        let value = json!({ "data": [] });
+
+        assert_eq!(select(&value, "$.data[3]").unwrap(), None);
+        assert_eq!(select(&value, "$.other.key").unwrap(), None);
+    }
+
+    #[test]
+    fn select_should_error_on_invalid_path() {
+        assert!(select(&json!({}), "$.data[0").is_err());
+        assert!(select(&json!({}), "$..data").is_err());
+    }
+
+    #[test]
+    fn select_should_return_root_for_empty_path() {
+        let value = json!([1]);
+
+        assert_eq!(select(&value, "$").unwrap(), Some(&value));
+    }
+}
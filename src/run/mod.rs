@@ -1,4 +1,13 @@
+mod assertions;
+mod batch;
+mod bench;
+mod captures;
 pub mod cli;
+pub mod history;
+mod json_path;
 pub mod mutators;
+mod response;
+pub mod result;
 pub mod settings;
+mod stream;
 mod utils;
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
-use crate::variables::{parse, Extractor, Inserter};
+use crate::variables::{
+    lexer::TemplateError, parse, placeholder::Placeholder, Extractor, Inserter,
+};
 
 pub struct RunMutators {}
 
@@ -8,13 +10,21 @@ static OPENING: &str = "r{";
 static CLOSING: &str = "}";
 
 impl Inserter for RunMutators {
-    fn insert(&self, templated: &str, value_map: &HashMap<String, String>) -> String {
-        parse::insert_variable_values(templated, value_map, OPENING, CLOSING)
+    fn opening(&self) -> &str {
+        OPENING
+    }
+
+    fn value(
+        &self,
+        placeholder: &Placeholder,
+        value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        value_map.get(&placeholder.name).cloned()
     }
 }
 
 impl Extractor for RunMutators {
-    fn extract(&self, templated: &str) -> Vec<String> {
+    fn extract(&self, templated: &str) -> Result<Vec<Placeholder>, TemplateError> {
         parse::extract_variable_names(templated, OPENING, CLOSING)
     }
 }
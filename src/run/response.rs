@@ -0,0 +1,508 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+// Wraps curl's write-out so it can be told apart from the response body on stdout
+static WRITE_OUT_START: &[u8] = b"\x1e[curld-write-out]";
+static WRITE_OUT_END: &[u8] = b"[/curld-write-out]\x1e";
+
+// Requests run on several threads at once, so each header file gets its own number
+static HEADER_FILES: AtomicUsize = AtomicUsize::new(0);
+
+// curl options that take a value, which is the next argument unless attached like -D-
+static SHORT_WITH_VALUE: &str = "AbcCdDeEFHKmoPQrTtuUwxXyYz";
+static LONG_WITH_VALUE: &[&str] = &[
+    "cacert",
+    "cert",
+    "config",
+    "connect-timeout",
+    "cookie",
+    "cookie-jar",
+    "data",
+    "data-ascii",
+    "data-binary",
+    "data-raw",
+    "data-urlencode",
+    "dump-header",
+    "form",
+    "form-string",
+    "header",
+    "json",
+    "key",
+    "max-time",
+    "output",
+    "proxy",
+    "proxy-user",
+    "range",
+    "referer",
+    "request",
+    "resolve",
+    "retry",
+    "upload-file",
+    "url",
+    "user",
+    "user-agent",
+    "write-out",
+];
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Status {
+    pub version: String,
+    pub code: u16,
+    pub reason: String,
+}
+
+/// The subset of curl's `%{json}` write-out that curld uses
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Metrics {
+    pub http_code: u16,
+    pub http_version: String,
+    pub content_type: Option<String>,
+    pub url_effective: String,
+    pub remote_ip: String,
+    pub num_redirects: u32,
+    pub size_download: f64,
+    pub size_upload: f64,
+    pub time_namelookup: f64,
+    pub time_connect: f64,
+    pub time_appconnect: f64,
+    pub time_pretransfer: f64,
+    pub time_starttransfer: f64,
+    pub time_total: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ResponseMeta {
+    pub status: Option<Status>,
+    pub headers: Vec<(String, String)>,
+    pub metrics: Option<Metrics>,
+}
+
+impl ResponseMeta {
+    pub fn status_code(&self) -> Option<u16> {
+        match (&self.status, &self.metrics) {
+            (Some(status), _) => Some(status.code),
+            (None, Some(metrics)) if metrics.http_code != 0 => Some(metrics.http_code),
+            _ => None,
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type").or_else(|| {
+            self.metrics
+                .as_ref()
+                .and_then(|metrics| metrics.content_type.as_deref())
+        })
+    }
+
+    pub fn summary_line(&self) -> Option<String> {
+        let mut parts = vec![match (&self.status, self.status_code()) {
+            (Some(status), _) => format!("{} {} {}", status.version, status.code, status.reason)
+                .trim_end()
+                .to_string(),
+            (None, Some(code)) => code.to_string(),
+            (None, None) => return None,
+        }];
+
+        if let Some(content_type) = self.content_type() {
+            parts.push(content_type.to_string());
+        }
+
+        if let Some(metrics) = &self.metrics {
+            parts.push(format!("{} bytes", metrics.size_download));
+            parts.push(format!("{:.0}ms", metrics.time_total * 1000.0));
+        }
+
+        Some(parts.join(" | "))
+    }
+}
+
+/// The extra curl arguments used to capture response metadata, skipping
+/// anything the user already asked curl to do themselves.
+pub struct Instrumentation {
+    header_file: Option<PathBuf>,
+    write_out: bool,
+}
+
+impl Instrumentation {
+    pub fn prepare(user_args: Vec<String>) -> (Vec<String>, Self) {
+        let header_file = if has_option(&user_args, 'D', "dump-header") {
+            None
+        } else {
+            Some(header_file_path())
+        };
+        let write_out = !has_option(&user_args, 'w', "write-out");
+
+        let mut args = Vec::new();
+        if let Some(path) = &header_file {
+            args.push("--dump-header".to_string());
+            args.push(path.to_string_lossy().to_string());
+        }
+        if write_out {
+            args.push("--write-out".to_string());
+            args.push(format!(
+                "{}%{{json}}{}",
+                String::from_utf8_lossy(WRITE_OUT_START),
+                String::from_utf8_lossy(WRITE_OUT_END)
+            ));
+        }
+        args.extend(user_args);
+
+        (
+            args,
+            Self {
+                header_file,
+                write_out,
+            },
+        )
+    }
+
+    pub fn splitter(&self) -> Option<WriteOutSplitter> {
+        if self.write_out {
+            Some(WriteOutSplitter::new())
+        } else {
+            None
+        }
+    }
+
+    pub fn finish(self, write_out: Option<Vec<u8>>) -> Option<ResponseMeta> {
+        let headers = self.header_file.and_then(|path| {
+            let raw = fs::read(&path).ok();
+            let _ = fs::remove_file(&path);
+            raw
+        });
+
+        let (status, headers) = match headers {
+            Some(raw) => parse_headers(&String::from_utf8_lossy(&raw)),
+            None => (None, Vec::new()),
+        };
+        let metrics = write_out.and_then(|json| serde_json::from_slice::<Metrics>(&json).ok());
+
+        if status.is_none() && metrics.is_none() {
+            return None;
+        }
+
+        Some(ResponseMeta {
+            status,
+            headers,
+            metrics,
+        })
+    }
+}
+
+fn header_file_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+
+    let number = HEADER_FILES.fetch_add(1, Ordering::Relaxed);
+
+    std::env::temp_dir().join(format!(
+        "curld-{}-{}-{}.headers",
+        process::id(),
+        number,
+        nanos
+    ))
+}
+
+/// Whether curl was given an option, reading the arguments the way curl does so a value
+/// such as the body in `-d -Dfoo` isn't taken for an option
+fn has_option(args: &[String], short: char, long: &str) -> bool {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--") {
+            if name == long {
+                return true;
+            }
+            if LONG_WITH_VALUE.contains(&name) {
+                args.next();
+            }
+        } else if let Some(flags) = arg.strip_prefix('-') {
+            // Short options can be grouped, like -sS, with a value attached to the last one
+            for (index, flag) in flags.char_indices() {
+                if flag == short {
+                    return true;
+                }
+                if SHORT_WITH_VALUE.contains(flag) {
+                    if index + flag.len_utf8() == flags.len() {
+                        args.next();
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Parses curl's dumped headers, only keeping the final response when there were
+/// redirects or informational responses.
+pub fn parse_headers(raw: &str) -> (Option<Status>, Vec<(String, String)>) {
+    let mut status = None;
+    let mut headers = Vec::new();
+
+    for line in raw.lines().map(|line| line.trim_end_matches('\r')) {
+        if line.starts_with("HTTP/") {
+            status = parse_status_line(line);
+            headers.clear();
+        } else if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    (status, headers)
+}
+
+fn parse_status_line(line: &str) -> Option<Status> {
+    let mut parts = line.splitn(3, ' ');
+    let version = parts.next()?.to_string();
+    let code = parts.next()?.parse().ok()?;
+    let reason = parts.next().unwrap_or_default().trim().to_string();
+
+    Some(Status {
+        version,
+        code,
+        reason,
+    })
+}
+
+/// Removes curld's write-out from curl's stdout as it is read, holding back just
+/// enough bytes to recognise a marker split across reads.
+pub struct WriteOutSplitter {
+    pending: Vec<u8>,
+    in_write_out: bool,
+    write_out: Option<Vec<u8>>,
+}
+
+impl WriteOutSplitter {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+            in_write_out: false,
+            write_out: None,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        let mut passthrough = Vec::new();
+
+        loop {
+            if self.in_write_out {
+                match find(&self.pending, WRITE_OUT_END) {
+                    Some(end) => {
+                        self.write_out = Some(self.pending[..end].to_vec());
+                        self.pending.drain(..end + WRITE_OUT_END.len());
+                        self.in_write_out = false;
+                    }
+                    None => break,
+                }
+            } else {
+                match find(&self.pending, WRITE_OUT_START) {
+                    Some(start) => {
+                        passthrough.extend(self.pending.drain(..start));
+                        self.pending.drain(..WRITE_OUT_START.len());
+                        self.in_write_out = true;
+                    }
+                    None => {
+                        let keep = partial_marker_len(&self.pending, WRITE_OUT_START);
+                        passthrough.extend(self.pending.drain(..self.pending.len() - keep));
+                        break;
+                    }
+                }
+            }
+        }
+
+        passthrough
+    }
+
+    /// Returns whatever was held back along with the last write-out seen
+    pub fn finish(mut self) -> (Vec<u8>, Option<Vec<u8>>) {
+        if self.in_write_out {
+            self.pending.clear();
+        }
+
+        (self.pending, self.write_out)
+    }
+
+    pub fn split(mut self, stdout: &[u8]) -> (Vec<u8>, Option<Vec<u8>>) {
+        let mut body = self.feed(stdout);
+        let (rest, write_out) = self.finish();
+        body.extend(rest);
+
+        (body, write_out)
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn partial_marker_len(bytes: &[u8], marker: &[u8]) -> usize {
+    (1..marker.len().min(bytes.len() + 1))
+        .rev()
+        .find(|len| bytes.ends_with(&marker[..*len]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(write_out: &str) -> Vec<u8> {
+        [WRITE_OUT_START, write_out.as_bytes(), WRITE_OUT_END].concat()
+    }
+
+    #[test]
+    fn parse_headers_should_use_last_response() {
+        let raw = "HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\n\r\nHTTP/2 200 \r\ncontent-type: application/json\r\nx-id: abc: def\r\n\r\n";
+
+        let (status, headers) = parse_headers(raw);
+
+        assert_eq!(
+            status,
+            Some(Status {
+                version: "HTTP/2".to_string(),
+                code: 200,
+                reason: "".to_string()
+            })
+        );
+        assert_eq!(
+            headers,
+            vec![
+                ("content-type".to_string(), "application/json".to_string()),
+                ("x-id".to_string(), "abc: def".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn splitter_should_remove_write_out_split_across_chunks() {
+        let mut stdout = b"{\"ok\": true}".to_vec();
+        stdout.extend(wrap(r#"{"http_code":200}"#));
+
+        let mut splitter = WriteOutSplitter::new();
+        let mut body: Vec<u8> = stdout
+            .chunks(3)
+            .flat_map(|chunk| splitter.feed(chunk))
+            .collect();
+        let (rest, write_out) = splitter.finish();
+        body.extend(rest);
+
+        assert_eq!(body, b"{\"ok\": true}");
+        assert_eq!(write_out.unwrap(), br#"{"http_code":200}"#);
+    }
+
+    #[test]
+    fn splitter_should_keep_body_around_each_write_out() {
+        let stdout = [
+            b"first".to_vec(),
+            wrap("{}"),
+            b"second".to_vec(),
+            wrap("{\"a\":1}"),
+        ]
+        .concat();
+
+        let (body, write_out) = WriteOutSplitter::new().split(&stdout);
+
+        assert_eq!(body, b"firstsecond");
+        assert_eq!(write_out.unwrap(), b"{\"a\":1}");
+    }
+
+    #[test]
+    fn prepare_should_not_override_user_write_out_or_headers() {
+        let user_args = vec![
+            "-w".to_string(),
+            "%{http_code}".to_string(),
+            "-D".to_string(),
+            "-".to_string(),
+        ];
+
+        let (args, instrumentation) = Instrumentation::prepare(user_args.clone());
+
+        assert_eq!(args, user_args);
+        assert!(instrumentation.splitter().is_none());
+    }
+
+    #[test]
+    fn has_option_should_only_match_options() {
+        let args =
+            |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+
+        assert!(has_option(
+            &args(&["-sD-", "example.com"]),
+            'D',
+            "dump-header"
+        ));
+        assert!(has_option(&args(&["-sSD", "h.txt"]), 'D', "dump-header"));
+        assert!(has_option(
+            &args(&["-o", "out", "--dump-header", "h"]),
+            'D',
+            "dump-header"
+        ));
+        assert!(!has_option(
+            &args(&["-d", "-Dfoo", "example.com"]),
+            'D',
+            "dump-header"
+        ));
+        assert!(!has_option(
+            &args(&["--data", "--dump-header"]),
+            'D',
+            "dump-header"
+        ));
+        assert!(!has_option(
+            &args(&["-H", "x: -w", "-Hw"]),
+            'w',
+            "write-out"
+        ));
+    }
+
+    #[test]
+    fn header_file_paths_should_be_unique() {
+        let paths: Vec<PathBuf> = (0..100).map(|_| header_file_path()).collect();
+
+        for (index, path) in paths.iter().enumerate() {
+            assert!(!paths[index + 1..].contains(path));
+        }
+    }
+
+    #[test]
+    fn summary_line_should_describe_response() {
+        let response = ResponseMeta {
+            status: Some(Status {
+                version: "HTTP/1.1".to_string(),
+                code: 404,
+                reason: "Not Found".to_string(),
+            }),
+            headers: vec![("Content-Type".to_string(), "text/html".to_string())],
+            metrics: Some(Metrics {
+                size_download: 12.0,
+                time_total: 0.25,
+                ..Default::default()
+            }),
+        };
+
+        assert_eq!(
+            response.summary_line().unwrap(),
+            "HTTP/1.1 404 Not Found | text/html | 12 bytes | 250ms"
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::time::Duration;
+
+use super::response::ResponseMeta;
+
+// Enough of the body to recognise binary content without scanning all of it
+static SNIFF_LEN: usize = 8 * 1024;
+
+static PREVIEW_LEN: usize = 32;
+
+// What `curl --fail` exits with when the server responds with an error status
+static HTTP_ERROR_EXIT_CODE: u8 = 22;
+
+static SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"%PDF-", "application/pdf"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"BZh", "application/x-bzip2"),
+    (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"\x00asm", "application/wasm"),
+    (b"\x7fELF", "application/x-elf"),
+];
+
+pub struct RunResult {
+    // None when curl was terminated by a signal
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub response: Option<ResponseMeta>,
+}
+
+impl RunResult {
+    pub fn new(
+        exit_code: Option<i32>,
+        duration: Duration,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    ) -> Self {
+        Self {
+            exit_code,
+            duration,
+            stdout,
+            stderr,
+            response: None,
+        }
+    }
+
+    pub fn with_response(mut self, response: Option<ResponseMeta>) -> Self {
+        self.response = response;
+        self
+    }
+
+    pub fn error_message(&self) -> Option<String> {
+        match self.exit_code {
+            Some(0) => None,
+            Some(code) => Some(format!(
+                "curl failed with exit code {code}: {reason}",
+                code = code,
+                reason = curl_error(code)
+            )),
+            None => Some("curl was terminated before it finished".to_string()),
+        }
+    }
+
+    /// Why the run failed, either curl itself or an error status from the server
+    pub fn failure(&self) -> Option<String> {
+        if let Some(message) = self.error_message() {
+            return Some(message);
+        }
+
+        match self.status_code() {
+            Some(code) if code >= 400 => Some(format!("status {}", code)),
+            _ => None,
+        }
+    }
+
+    /// The code to exit with when the run failed, curl's own when curl failed and the
+    /// one `curl --fail` uses for an error status
+    pub fn failure_exit_code(&self) -> Option<u8> {
+        match self.exit_code {
+            Some(0) => self.failure().map(|_| HTTP_ERROR_EXIT_CODE),
+            Some(code) => Some(code as u8),
+            None => Some(1),
+        }
+    }
+
+    pub fn status_code(&self) -> Option<u16> {
+        self.response
+            .as_ref()
+            .and_then(|response| response.status_code())
+    }
+
+    /// The status and duration, padded to line up in summaries
+    pub fn status_row(&self) -> String {
+        let status = self
+            .status_code()
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        format!(
+            "{:>3}  {:>6}",
+            status,
+            format!("{}ms", self.duration.as_millis())
+        )
+    }
+
+    /// curl's output, falling back to stderr when nothing was written to stdout
+    pub fn output(&self) -> &[u8] {
+        if !self.stdout.is_empty() {
+            return &self.stdout;
+        }
+
+        &self.stderr
+    }
+
+    pub fn is_binary(&self) -> bool {
+        is_binary(self.output())
+    }
+
+    pub fn summary(&self) -> String {
+        let output = self.output();
+
+        format!(
+            "Binary response: {size} bytes, {content_type}\n{preview}",
+            size = output.len(),
+            content_type = detect_type(output),
+            preview = hex_preview(output)
+        )
+    }
+}
+
+pub fn curl_error(code: i32) -> &'static str {
+    match code {
+        1 => "Unsupported protocol",
+        2 => "Failed to initialize",
+        3 => "URL malformed",
+        4 => "A feature or option that was needed is not enabled in this curl",
+        5 => "Couldn't resolve proxy",
+        6 => "Couldn't resolve host",
+        7 => "Failed to connect to host",
+        8 => "Weird server reply",
+        9 => "Access denied to remote resource",
+        16 => "HTTP/2 framing layer error",
+        18 => "Partial file, only a part of the response was received",
+        22 => "HTTP page not retrieved, the server returned an error status",
+        23 => "Write error, curl couldn't write data to a local file",
+        25 => "Upload failed",
+        26 => "Read error, curl couldn't read a local file",
+        27 => "Out of memory",
+        28 => "Operation timeout",
+        33 => "HTTP range error",
+        34 => "HTTP post error",
+        35 => "SSL connect error",
+        36 => "Bad download resume",
+        37 => "Couldn't read the file",
+        42 => "Aborted by callback",
+        43 => "Internal error",
+        45 => "Interface error, the specified outgoing interface could not be used",
+        47 => "Too many redirects",
+        48 => "Unknown option passed to curl",
+        52 => "Empty reply from server",
+        53 => "SSL crypto engine not found",
+        54 => "Cannot set SSL crypto engine as default",
+        55 => "Failed sending network data",
+        56 => "Failure in receiving network data",
+        58 => "Problem with the local certificate",
+        59 => "Couldn't use the specified SSL cipher",
+        60 => "Peer certificate cannot be authenticated with known CA certificates",
+        61 => "Unrecognized transfer encoding",
+        63 => "Maximum file size exceeded",
+        65 => "Sending the data requires a rewind that failed",
+        66 => "Failed to initialise SSL engine",
+        67 => "The user name, password, or similar was not accepted",
+        75 => "Character conversion failed",
+        77 => "Problem reading the SSL CA cert",
+        78 => "The resource referenced in the URL does not exist",
+        80 => "Failed to shut down the SSL connection",
+        82 => "Could not load CRL file",
+        83 => "Issuer check failed",
+        89 => "No connection available, the session will be queued",
+        90 => "SSL public key does not match pinned public key",
+        91 => "Invalid SSL certificate status",
+        92 => "Stream error in the HTTP/2 framing layer",
+        95 => "HTTP/3 layer error",
+        96 => "QUIC connection error",
+        97 => "Proxy handshake error",
+        98 => "A client-side certificate is required to complete the TLS handshake",
+        99 => "Poll or select returned fatal error",
+        _ => "Unknown curl error",
+    }
+}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sniffed = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if sniffed.contains(&0) {
+        return true;
+    }
+
+    match std::str::from_utf8(sniffed) {
+        Ok(_) => false,
+        // A character cut in half by the sniff window doesn't make the content binary
+        Err(error) => error.error_len().is_some(),
+    }
+}
+
+pub fn detect_type(bytes: &[u8]) -> &'static str {
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+        .map(|(_, content_type)| *content_type)
+        .unwrap_or("application/octet-stream")
+}
+
+pub fn hex_preview(bytes: &[u8]) -> String {
+    bytes[..bytes.len().min(PREVIEW_LEN)]
+        .chunks(16)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|byte| match byte {
+                    0x20..=0x7e => *byte as char,
+                    _ => '.',
+                })
+                .collect();
+
+            format!("{:08x}  {:<47}  |{}|", line * 16, hex.join(" "), ascii)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::response::parse_headers;
+
+    #[test]
+    fn is_binary_should_accept_utf8_text() {
+        assert!(!is_binary("{\"name\": \"crème brûlée\"}".as_bytes()));
+    }
+
+    #[test]
+    fn is_binary_should_detect_invalid_utf8_and_nul() {
+        assert!(is_binary(b"\x1f\x8b\x08\x00\xff"));
+        assert!(is_binary(b"text\x00with nul"));
+    }
+
+    #[test]
+    fn detect_type_should_use_magic_bytes() {
+        assert_eq!(detect_type(b"\x89PNG\r\n\x1a\n\x00\x00"), "image/png");
+        assert_eq!(detect_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "), "image/webp");
+        assert_eq!(detect_type(b"\x0a\x04test"), "application/octet-stream");
+    }
+
+    #[test]
+    fn hex_preview_should_show_offset_hex_and_ascii() {
+        let preview = hex_preview(b"GIF89a\x01\x00");
+
+        assert_eq!(
+            preview,
+            "00000000  47 49 46 38 39 61 01 00                          |GIF89a..|"
+        );
+    }
+
+    #[test]
+    fn output_should_fall_back_to_stderr() {
+        let result = RunResult::new(
+            Some(6),
+            Duration::ZERO,
+            Vec::new(),
+            b"curl: (6) Could not resolve host".to_vec(),
+        );
+
+        assert_eq!(result.output(), b"curl: (6) Could not resolve host");
+    }
+
+    #[test]
+    fn error_message_should_map_curl_exit_code() {
+        let result = RunResult::new(Some(7), Duration::ZERO, Vec::new(), Vec::new());
+
+        assert_eq!(result.failure_exit_code(), Some(7));
+        assert_eq!(
+            result.error_message().unwrap(),
+            "curl failed with exit code 7: Failed to connect to host"
+        );
+    }
+
+    #[test]
+    fn error_message_should_be_empty_on_success() {
+        let result = RunResult::new(Some(0), Duration::ZERO, b"ok".to_vec(), Vec::new());
+
+        assert_eq!(result.failure_exit_code(), None);
+        assert!(result.error_message().is_none());
+    }
+
+    #[test]
+    fn failure_exit_code_should_fail_on_error_status() {
+        let result = |exit_code, status: &str| {
+            let raw = format!("HTTP/1.1 {}\r\n", status);
+            RunResult::new(exit_code, Duration::ZERO, Vec::new(), Vec::new()).with_response(Some(
+                ResponseMeta {
+                    status: parse_headers(&raw).0,
+                    ..Default::default()
+                },
+            ))
+        };
+
+        assert_eq!(result(Some(0), "200 OK").failure_exit_code(), None);
+        assert_eq!(result(Some(0), "302 Found").failure_exit_code(), None);
+        assert_eq!(
+            result(Some(0), "404 Not Found").failure_exit_code(),
+            Some(22)
+        );
+        assert_eq!(
+            result(Some(0), "500 Internal Server Error").failure_exit_code(),
+            Some(22)
+        );
+        assert_eq!(result(Some(7), "200 OK").failure_exit_code(), Some(7));
+        assert_eq!(result(None, "200 OK").failure_exit_code(), Some(1));
+    }
+}
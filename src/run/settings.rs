@@ -9,7 +9,10 @@ use crate::{
     common::CurldCommand, settings::traits::StoredSettings, variables::builder::VariablesBuilder,
 };
 
-use super::mutators::RunMutators;
+use super::{
+    assertions::Expectations, batch::matches_glob, captures::Capture, history::HistoryEntry,
+    mutators::RunMutators,
+};
 
 pub static RUN_MODULE: &str = "run";
 
@@ -22,30 +25,115 @@ pub struct RunManager<'a> {
 #[derive(Deserialize, Serialize, Default)]
 pub struct RunSettings {
     #[serde(default)]
-    saved: HashMap<String, CurldCommand>,
+    saved: HashMap<String, SavedCommand>,
 
     #[serde(default)]
     history_len: usize,
 
     #[serde(default)]
-    history: VecDeque<CurldCommand>,
+    history: VecDeque<HistoryEntry>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct SavedCommand {
+    // Flattened so commands saved before expectations existed still load
+    #[serde(flatten)]
+    pub command: CurldCommand,
+
+    #[serde(default, skip_serializing_if = "Expectations::is_empty")]
+    pub expect: Expectations,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub captures: Vec<Capture>,
+
+    // The last value used for each placeholder, offered as the default when asked again
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub last_values: HashMap<String, String>,
 }
 
 impl<'a> RunManager<'a> {
     pub fn add_saved(&mut self, id: String, history: CurldCommand) {
-        self.settings.saved.insert(id, history);
+        // Saving over an existing command keeps what was attached to it
+        self.settings.saved.entry(id).or_default().command = history;
+        self.save_to_parent();
     }
 
-    pub fn get_saved(&self, id: &String) -> Option<&CurldCommand> {
+    pub fn get_saved(&self, id: &String) -> Option<&SavedCommand> {
         self.settings.saved.get(id)
     }
 
+    pub fn set_expectations(&mut self, id: &String, expect: Expectations) -> Result<(), String> {
+        self.get_saved_mut(id)?.expect = expect;
+        self.save_to_parent();
+        Ok(())
+    }
+
+    pub fn add_capture(&mut self, id: &String, capture: Capture) -> Result<(), String> {
+        let captures = &mut self.get_saved_mut(id)?.captures;
+        // A variable is only captured from one place
+        captures.retain(|existing| existing.variable != capture.variable);
+        captures.push(capture);
+
+        self.save_to_parent();
+        Ok(())
+    }
+
+    pub fn clear_captures(&mut self, id: &String) -> Result<(), String> {
+        self.get_saved_mut(id)?.captures.clear();
+        self.save_to_parent();
+        Ok(())
+    }
+
+    fn get_saved_mut(&mut self, id: &String) -> Result<&mut SavedCommand, String> {
+        self.settings
+            .saved
+            .get_mut(id)
+            .ok_or_else(|| format!("Could not find saved command {}", id))
+    }
+
+    pub fn remember_values(&mut self, id: &String, values: &HashMap<String, String>) {
+        if let Ok(saved) = self.get_saved_mut(id) {
+            saved.last_values.extend(values.clone());
+            self.save_to_parent();
+        }
+    }
+
     pub fn get_saved_keys(&self) -> Vec<String> {
         self.settings.saved.keys().map(|k| k.to_string()).collect()
     }
 
-    pub fn insert_history(&mut self, cmd: CurldCommand) {
-        self.settings.history.push_front(cmd);
+    /// Resolves ids and globs like `orders/*` to saved ids, keeping the order they were given in
+    pub fn find_saved(&self, patterns: &[String]) -> Result<Vec<String>, String> {
+        let mut keys = self.get_saved_keys();
+        keys.sort();
+
+        let mut found: Vec<String> = Vec::new();
+        for pattern in patterns {
+            let matched: Vec<String> = if pattern.contains(['*', '?']) {
+                keys.iter()
+                    .filter(|key| matches_glob(pattern, key))
+                    .cloned()
+                    .collect()
+            } else {
+                keys.iter().filter(|key| *key == pattern).cloned().collect()
+            };
+
+            if matched.is_empty() {
+                return Err(format!("Could not find saved command {}", pattern));
+            }
+
+            for id in matched {
+                if !found.contains(&id) {
+                    found.push(id);
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    pub fn insert_history(&mut self, entry: HistoryEntry) {
+        self.settings.history.push_front(entry);
         self.settings.history.truncate(self.settings.history_len);
 
         self.save_to_parent();
@@ -56,17 +144,17 @@ impl<'a> RunManager<'a> {
             .history
             .iter()
             .enumerate()
-            .map(|(index, curld)| {
+            .map(|(index, entry)| {
                 format!(
                     "{index}| {cmd}",
                     index = index,
-                    cmd = builder.to_string(curld)
+                    cmd = builder.to_string(&entry.command)
                 )
             })
             .collect()
     }
 
-    pub fn get_history_entry(&self, index: usize) -> Option<&CurldCommand> {
+    pub fn get_history_entry(&self, index: usize) -> Option<&HistoryEntry> {
         self.settings.history.get(index)
     }
 
@@ -0,0 +1,254 @@
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Instant;
+
+use crate::common::IO;
+
+use super::response::{Instrumentation, WriteOutSplitter};
+use super::result::{is_binary, RunResult};
+use super::utils::spawn_error;
+
+// Only the start of a streamed response is kept in memory
+pub static CAPTURE_LIMIT: usize = 64 * 1024;
+
+static CHUNK_SIZE: usize = 8 * 1024;
+
+pub enum Sink {
+    Terminal,
+    File(String),
+}
+
+impl Sink {
+    pub fn new(output: &Option<String>) -> Self {
+        match output {
+            Some(path) => Sink::File(path.to_owned()),
+            None => Sink::Terminal,
+        }
+    }
+
+    fn writer(&self) -> Result<Box<dyn Write>, String> {
+        match self {
+            Sink::Terminal => Ok(Box::new(io::stdout())),
+            Sink::File(path) => match File::create(path) {
+                Ok(file) => Ok(Box::new(file)),
+                Err(error) => Err(format!("Unable to create output file {}: {}", path, error)),
+            },
+        }
+    }
+
+    fn curl_args(&self) -> Vec<String> {
+        // curl buffers its output when it isn't writing to a terminal
+        let mut args = vec!["--no-buffer".to_string()];
+
+        // Without a terminal curl shows its progress meter, which would be mixed into the body
+        if let Sink::Terminal = self {
+            args.push("--silent".to_string());
+            args.push("--show-error".to_string());
+        }
+
+        args
+    }
+}
+
+pub struct BoundedBuffer {
+    bytes: Vec<u8>,
+    limit: usize,
+}
+
+impl BoundedBuffer {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            bytes: Vec::new(),
+            limit,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        let remaining = self.limit - self.bytes.len();
+        self.bytes
+            .extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Runs curl and forwards its stdout to the sink and its stderr to the terminal as they arrive.
+/// Binary content isn't written to a terminal, a summary is shown once curl finishes instead.
+pub fn stream_with_args(args: Vec<String>, sink: &Sink) -> Result<RunResult, String> {
+    // Opened first so a bad path stops the run before curl is started
+    let mut writer = sink.writer()?;
+    let (args, instrumentation) = Instrumentation::prepare(args);
+    let started = Instant::now();
+    let child = Command::new("curl")
+        .args(sink.curl_args())
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = child.map_err(spawn_error)?;
+
+    let stderr = child.stderr.take().expect("curl stderr was not piped");
+    let stderr_handle = thread::spawn(move || forward(stderr, &mut io::stderr(), false, None));
+
+    let stdout = child.stdout.take().expect("curl stdout was not piped");
+    let guard_binary = matches!(sink, Sink::Terminal) && IO::is_terminal();
+    let stdout_forwarded = forward(
+        stdout,
+        &mut writer,
+        guard_binary,
+        instrumentation.splitter(),
+    );
+
+    let stderr_forwarded = stderr_handle
+        .join()
+        .expect("Unable to read stderr from curl");
+
+    let status = match child.wait() {
+        Ok(status) => status,
+        Err(error) => {
+            // Removes the header file curl wrote
+            instrumentation.finish(None);
+            return Err(format!("Unable to wait for curl: {}", error));
+        }
+    };
+
+    let result = RunResult::new(
+        status.code(),
+        started.elapsed(),
+        stdout_forwarded.captured.into_bytes(),
+        stderr_forwarded.captured.into_bytes(),
+    )
+    .with_response(instrumentation.finish(stdout_forwarded.write_out));
+    if stdout_forwarded.suppressed {
+        IO::output(&result.summary());
+    }
+
+    Ok(result)
+}
+
+struct Forwarded {
+    captured: BoundedBuffer,
+    suppressed: bool,
+    write_out: Option<Vec<u8>>,
+}
+
+/// Copies everything from the reader to the writer, keeping a bounded copy.
+/// When guarding against binary content, nothing is written if the first chunk is binary.
+fn forward(
+    mut reader: impl Read,
+    writer: &mut impl Write,
+    guard_binary: bool,
+    mut splitter: Option<WriteOutSplitter>,
+) -> Forwarded {
+    let mut captured = BoundedBuffer::new(CAPTURE_LIMIT);
+    let mut suppressed: Option<bool> = None;
+    let mut emit = |data: &[u8]| {
+        if data.is_empty() {
+            return;
+        }
+
+        let suppress = *suppressed.get_or_insert_with(|| guard_binary && is_binary(data));
+        if !suppress {
+            // Keep draining curl even if the receiving end has gone away, so it doesn't block
+            let _ = writer.write_all(data).and_then(|_| writer.flush());
+        }
+        captured.push(data);
+    };
+
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+
+        match &mut splitter {
+            Some(splitter) => emit(&splitter.feed(&chunk[..read])),
+            None => emit(&chunk[..read]),
+        }
+    }
+
+    let write_out = splitter.and_then(|splitter| {
+        let (rest, write_out) = splitter.finish();
+        emit(&rest);
+        write_out
+    });
+
+    Forwarded {
+        captured,
+        suppressed: suppressed.unwrap_or(false),
+        write_out,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_buffer_should_keep_start_of_output() {
+        let mut buffer = BoundedBuffer::new(5);
+        buffer.push(b"abc");
+        buffer.push(b"defg");
+
+        assert_eq!(buffer.into_bytes(), b"abcde");
+    }
+
+    #[test]
+    fn forward_should_copy_everything_and_capture_bounded() {
+        let input = vec![b'a'; CAPTURE_LIMIT + 10];
+        let mut written: Vec<u8> = Vec::new();
+
+        let forwarded = forward(input.as_slice(), &mut written, true, None);
+
+        assert_eq!(written.len(), CAPTURE_LIMIT + 10);
+        assert_eq!(forwarded.captured.into_bytes().len(), CAPTURE_LIMIT);
+        assert!(!forwarded.suppressed);
+    }
+
+    #[test]
+    fn forward_should_hold_back_binary_when_guarded() {
+        let input = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+        let mut written: Vec<u8> = Vec::new();
+
+        let forwarded = forward(&input[..], &mut written, true, None);
+
+        assert!(written.is_empty());
+        assert_eq!(forwarded.captured.into_bytes(), input.to_vec());
+        assert!(forwarded.suppressed);
+    }
+
+    #[test]
+    fn forward_should_strip_write_out() {
+        let input = b"body\x1e[curld-write-out]{\"http_code\":201}[/curld-write-out]\x1e";
+        let mut written: Vec<u8> = Vec::new();
+
+        let forwarded = forward(
+            &input[..],
+            &mut written,
+            false,
+            Some(WriteOutSplitter::new()),
+        );
+
+        assert_eq!(written, b"body");
+        assert_eq!(forwarded.write_out.unwrap(), b"{\"http_code\":201}");
+    }
+
+    #[test]
+    fn stream_should_fail_before_running_curl_when_the_output_cannot_be_created() {
+        let sink = Sink::new(&Some("/nonexistent/curld/out.bin".to_string()));
+
+        let error = stream_with_args(vec!["http://localhost:1".to_string()], &sink).err();
+
+        assert!(error
+            .unwrap_or_default()
+            .starts_with("Unable to create output file /nonexistent/curld/out.bin"));
+    }
+}
@@ -1,15 +1,38 @@
+use std::io;
 use std::process::Command;
+use std::time::Instant;
 
-pub fn run_with_args(args: Vec<String>) -> String {
+use super::response::Instrumentation;
+use super::result::RunResult;
+
+pub fn run_with_args(args: Vec<String>) -> Result<RunResult, String> {
+    let (args, instrumentation) = Instrumentation::prepare(args);
+    let started = Instant::now();
     let output = Command::new("curl").args(args).output();
     match output {
         Ok(cmd_out) => {
-            if !cmd_out.stdout.is_empty() {
-                return String::from_utf8(cmd_out.stdout).unwrap();
-            }
+            let (stdout, write_out) = match instrumentation.splitter() {
+                Some(splitter) => splitter.split(&cmd_out.stdout),
+                None => (cmd_out.stdout, None),
+            };
+
+            Ok(RunResult::new(
+                cmd_out.status.code(),
+                started.elapsed(),
+                stdout,
+                cmd_out.stderr,
+            )
+            .with_response(instrumentation.finish(write_out)))
+        }
+        Err(error) => Err(spawn_error(error)),
+    }
+}
 
-            String::from_utf8(cmd_out.stderr).unwrap()
+pub fn spawn_error(error: io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => {
+            "Unable to run curl, make sure it is installed and on your PATH".to_string()
         }
-        Err(msg) => panic!("{:?}", msg),
+        _ => format!("Unable to run curl: {}", error),
     }
 }
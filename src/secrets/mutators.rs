@@ -0,0 +1,152 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::common::IO;
+use crate::variables::{
+    lexer::{tokenize, TemplateError, Token},
+    placeholder::Placeholder,
+    Extractor, Inserter,
+};
+
+static OPENING: &str = "s{";
+static CLOSING: &str = "}";
+
+/// Fills `s{name}` with values typed without echo. They are only kept in memory for this
+/// run, never in a command's value map, so they can't reach the history or saved commands.
+pub struct SecretMutator {
+    values: RefCell<HashMap<String, String>>,
+    // Secrets the commands use that have no value yet, asked for once the others have been
+    wanted: RefCell<Vec<Placeholder>>,
+}
+
+impl SecretMutator {
+    pub fn new() -> Self {
+        Self {
+            values: RefCell::new(HashMap::new()),
+            wanted: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The environment variable a secret can be given in, like `CURLD_SECRET_API_KEY`
+    /// for `s{api-key}`
+    pub fn env_name(name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|character| match character.is_ascii_alphanumeric() {
+                true => character.to_ascii_uppercase(),
+                false => '_',
+            })
+            .collect();
+        format!("CURLD_SECRET_{}", name)
+    }
+}
+
+impl Inserter for SecretMutator {
+    fn opening(&self) -> &str {
+        OPENING
+    }
+
+    fn value(
+        &self,
+        placeholder: &Placeholder,
+        _value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        self.values.borrow().get(&placeholder.name).cloned()
+    }
+}
+
+impl Extractor for SecretMutator {
+    // Secrets are never returned to be prompted for with the other values, so they don't
+    // end up in the value map. They are filled in by `fill` instead.
+    fn extract(&self, template: &str) -> Result<Vec<Placeholder>, TemplateError> {
+        let mut wanted = self.wanted.borrow_mut();
+        for token in tokenize(template, OPENING, CLOSING)? {
+            if let Token::Placeholder { placeholder, .. } = token {
+                let known = self.values.borrow().contains_key(&placeholder.name)
+                    || wanted.iter().any(|other| other.name == placeholder.name);
+                if !known {
+                    wanted.push(placeholder);
+                }
+            }
+        }
+
+        Ok(Vec::new())
+    }
+
+    // Taken from the values given up front, then the environment, and asked for last
+    fn fill(&self, given: &HashMap<String, String>) -> Result<(), String> {
+        let mut missing = Vec::new();
+        for placeholder in self.wanted.take() {
+            let secret = given
+                .get(&placeholder.name)
+                .cloned()
+                .or_else(|| std::env::var(SecretMutator::env_name(&placeholder.name)).ok());
+            let secret = match secret {
+                Some(secret) => secret,
+                None if IO::accepts_input() => {
+                    IO::prompt_secret(&format!("Enter secret for {}: ", placeholder.name))
+                }
+                None => {
+                    missing.push(placeholder);
+                    continue;
+                }
+            };
+            self.values.borrow_mut().insert(placeholder.name, secret);
+        }
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let variables: Vec<String> = missing
+            .iter()
+            .map(|placeholder| SecretMutator::env_name(&placeholder.name))
+            .collect();
+        Err(format!(
+            "Missing secrets for {}, give them with --var, --vars-file, --vars-stdin or {}",
+            Placeholder::names(&missing),
+            variables.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_should_be_inserted_without_prompting_twice() {
+        let mutator = SecretMutator {
+            values: RefCell::new(HashMap::from([(
+                "password".to_string(),
+                "hunter2".to_string(),
+            )])),
+            wanted: RefCell::new(Vec::new()),
+        };
+
+        let placeholders = mutator.extract("-u ada:s{password}").unwrap();
+        let inserted = mutator.value(&Placeholder::new("password"), &HashMap::new());
+
+        assert!(placeholders.is_empty());
+        assert_eq!(inserted, Some("hunter2".to_string()));
+    }
+
+    #[test]
+    fn fill_should_take_given_values_then_the_environment() {
+        std::env::set_var("CURLD_SECRET_TEST_API_KEY", "sk_123");
+        let mutator = SecretMutator::new();
+
+        mutator
+            .extract("-u ada:s{test_password} -H 'Key: s{test-api-key}'")
+            .unwrap();
+        let given = HashMap::from([("test_password".to_string(), "hunter2".to_string())]);
+        mutator.fill(&given).unwrap();
+
+        assert_eq!(
+            mutator.value(&Placeholder::new("test_password"), &HashMap::new()),
+            Some("hunter2".to_string())
+        );
+        assert_eq!(
+            mutator.value(&Placeholder::new("test-api-key"), &HashMap::new()),
+            Some("sk_123".to_string())
+        );
+    }
+}
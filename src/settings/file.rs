@@ -50,6 +50,10 @@ fn get_config_dir() -> String {
 }
 pub fn overwrite_file(file_loc: &String, content: &str) {
     let mut file = get_file(file_loc);
+    // The file is shared with reads, so it is only truncated when being overwritten
+    if let Err(error) = file.set_len(0) {
+        panic!("Unable to truncate file due to {:?}", error)
+    }
     match file.write_all(content.as_bytes()) {
         Ok(file) => file,
         Err(error) => panic!("Unable to overwrite file due to {:?}", error),
@@ -79,6 +83,7 @@ pub fn create_parent_dirs(file_loc: &String) {
 fn get_file(file_loc: &String) -> File {
     let file_result = File::options()
         .create(true)
+        .truncate(false)
         .write(true)
         .read(true)
         .open(file_loc);
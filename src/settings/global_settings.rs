@@ -21,10 +21,7 @@ struct SerializeSettings {
 
 impl<T: de::DeserializeOwned + Serialize> StoredSettings<T> for GlobalSettings {
     fn get_module(&self, module_name: &str) -> Option<T> {
-        let module_settings = match self.settings.module_settings.get(module_name) {
-            Some(module_settings) => module_settings,
-            None => return None,
-        };
+        let module_settings = self.settings.module_settings.get(module_name)?;
 
         let module_settings: T = match from_value(module_settings.to_owned()) {
             Ok(settings) => settings,
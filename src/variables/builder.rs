@@ -1,6 +1,64 @@
-use crate::common::CurldCommand;
+use std::{collections::HashMap, fmt};
 
-use super::{Extractor, Inserter};
+use crate::common::{CurldCommand, IO};
+
+use super::{
+    lexer::TemplateError,
+    placeholder::Placeholder,
+    template::{self, Template},
+    Extractor, Inserter,
+};
+
+static CLOSING: &str = "}";
+
+#[derive(Debug)]
+pub enum ExtractError {
+    Template(TemplateError),
+    // Placeholders with no value, grouped by where their value should have come from
+    Unresolved(Vec<(String, Vec<Placeholder>)>),
+}
+
+impl ExtractError {
+    /// The error on one line, without the template excerpt
+    pub fn message(&self) -> String {
+        match self {
+            ExtractError::Template(error) => error.message.to_owned(),
+            ExtractError::Unresolved(missing) => format!(
+                "no value for {}",
+                missing
+                    .iter()
+                    .map(|(source, placeholders)| format!(
+                        "{} in the {}",
+                        Placeholder::names(placeholders),
+                        source
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Template(error) => write!(f, "{}", error),
+            ExtractError::Unresolved(missing) => {
+                write!(f, "Unresolved placeholders:")?;
+                for (source, placeholders) in missing {
+                    write!(f, "\n  {}: {}", source, Placeholder::names(placeholders))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<TemplateError> for ExtractError {
+    fn from(error: TemplateError) -> Self {
+        ExtractError::Template(error)
+    }
+}
 
 #[derive(Clone)]
 pub struct VariablesBuilder<'a> {
@@ -16,38 +74,187 @@ impl<'a> VariablesBuilder<'a> {
         }
     }
 
-    pub fn extract(&mut self, user_args: &Vec<String>) -> Vec<String> {
-        user_args
+    /// Finds the placeholders to prompt for. Placeholders with no value in their source are
+    /// all reported before anything runs, and are prompted for when they can be saved.
+    pub fn extract(&mut self, user_args: &[String]) -> Result<Vec<Placeholder>, ExtractError> {
+        let mut keys = Vec::new();
+        let mut missing = vec![Vec::new(); self.extractors.len()];
+        for (index, input) in user_args.iter().enumerate() {
+            // Placeholders inside values that are templates themselves are extracted too
+            let expanded = self
+                .compile(input)
+                .map_err(|error| error.in_argument(index + 1))?
+                .written();
+
+            for (position, extractor) in self.extractors.iter().enumerate() {
+                let extracted = extractor
+                    .extract(&expanded)
+                    .map_err(|error| error.in_argument(index + 1))?;
+                match extractor.missing_from() {
+                    Some(_) => missing[position].extend(extracted),
+                    None => keys.extend(extracted),
+                }
+            }
+        }
+
+        let missing: Vec<(&dyn Extractor, Vec<Placeholder>)> = self
+            .extractors
             .iter()
-            .flat_map(|input| {
-                self.extractors
+            .zip(missing)
+            .filter(|(_, placeholders)| !placeholders.is_empty())
+            .map(|(extractor, placeholders)| (*extractor, Placeholder::dedupe(placeholders)))
+            .collect();
+
+        if !missing.is_empty() {
+            let unresolved = ExtractError::Unresolved(
+                missing
                     .iter()
-                    .flat_map(|extractor| extractor.extract(input))
-                    .collect::<Vec<String>>()
-            })
-            .collect()
+                    .map(|(extractor, placeholders)| {
+                        let source = extractor.missing_from().unwrap_or_default();
+                        (source, placeholders.to_owned())
+                    })
+                    .collect(),
+            );
+
+            let savable = missing.iter().all(|(extractor, _)| extractor.can_save());
+            if !savable || !IO::is_interactive() {
+                return Err(unresolved);
+            }
+
+            IO::error(&unresolved.to_string());
+            for (extractor, placeholders) in &missing {
+                let source = extractor.missing_from().unwrap_or_default();
+                for placeholder in placeholders {
+                    if !VariablesBuilder::prompt_to_save(*extractor, placeholder, &source) {
+                        return Err(unresolved);
+                    }
+                }
+            }
+        }
+
+        Ok(Placeholder::dedupe(keys))
+    }
+
+    // Nothing entered leaves the placeholder unresolved. Values the extractor can't keep,
+    // like ones that would refer back to themselves, are asked for again
+    fn prompt_to_save(extractor: &dyn Extractor, placeholder: &Placeholder, source: &str) -> bool {
+        loop {
+            let value = match placeholder.choices.is_empty() {
+                true => IO::prompt(&format!(
+                    "Enter value for {} to save in the {}, or nothing to stop: ",
+                    placeholder.name, source
+                )),
+                false => {
+                    let message = format!(
+                        "Choose a value for {} to save in the {}",
+                        placeholder.name, source
+                    );
+                    match IO::select(&message, &placeholder.choices, 0) {
+                        Ok(value) => value,
+                        Err(message) => {
+                            IO::error(&message);
+                            return false;
+                        }
+                    }
+                }
+            };
+            if value.is_empty() {
+                return false;
+            }
+
+            match placeholder
+                .validate(&value)
+                .and_then(|_| extractor.save(&placeholder.name, &value))
+            {
+                Ok(()) => return true,
+                Err(message) => IO::error(&message),
+            }
+        }
+    }
+
+    /// Fills in the values extractors keep themselves, like secrets, for the commands
+    /// extracted so far
+    pub fn fill(&self, given: &HashMap<String, String>) -> Result<(), String> {
+        self.extractors
+            .iter()
+            .try_for_each(|extractor| extractor.fill(given))
     }
 
+    /// The arguments to send, with placeholders that have no value falling back to their default
     pub fn insert(&self, curld: &CurldCommand) -> Vec<String> {
         curld
             .user_args
             .iter()
             .map(|input| {
-                self.inserters
-                    .iter()
-                    .fold(input.to_owned(), |acc, inserter| {
-                        inserter.insert(&acc, &curld.value_map)
-                    })
+                self.render(input, &|inserter, placeholder| {
+                    inserter
+                        .value(placeholder, &curld.value_map)
+                        .or_else(|| placeholder.default.clone())
+                })
             })
             .collect()
     }
 
+    /// The command as it is shown, such as in the history. Placeholders an inserter doesn't
+    /// show a value for are kept as they were written.
     pub fn to_string(&self, curld: &CurldCommand) -> String {
-        self.inserters
+        curld
+            .user_args
             .iter()
-            .fold(curld.user_args.join(" "), |acc, inserter| {
-                inserter.insert(&acc, &curld.value_map)
+            .map(|input| {
+                self.render(input, &|inserter, placeholder| {
+                    inserter.display(placeholder, &curld.value_map)
+                })
             })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Escapes the openers in a value that must be sent exactly as it is, such as text
+    /// captured from a response, so it is never read as a placeholder
+    pub fn escape(&self, value: &str) -> String {
+        template::escape(value, &self.openings())
+    }
+
+    fn openings(&self) -> Vec<&str> {
+        self.inserters
+            .iter()
+            .map(|inserter| inserter.opening())
+            .collect()
+    }
+
+    // Every namespace is read in the same pass, so a value is never read as a template
+    fn compile(&self, input: &str) -> Result<Template, TemplateError> {
+        let openings = self.openings();
+        let no_values = HashMap::new();
+
+        Template::compile(input, &openings, CLOSING)?.expand(&|opening, placeholder| {
+            self.inserter(opening)
+                .filter(|inserter| inserter.expands())
+                .and_then(|inserter| inserter.value(placeholder, &no_values))
+        })
+    }
+
+    fn render(
+        &self,
+        input: &str,
+        value: &dyn Fn(&dyn Inserter, &Placeholder) -> Option<String>,
+    ) -> String {
+        // Templates that can't be read are reported when they are extracted
+        match self.compile(input) {
+            Ok(template) => template.render(&|opening, placeholder| {
+                self.inserter(opening)
+                    .and_then(|inserter| value(inserter, placeholder))
+            }),
+            Err(_) => input.to_owned(),
+        }
+    }
+
+    fn inserter(&self, opening: &str) -> Option<&'a dyn Inserter> {
+        self.inserters
+            .iter()
+            .find(|inserter| inserter.opening() == opening)
+            .copied()
     }
 
     pub fn add_inserter(&mut self, inserter: &'a dyn Inserter) {
@@ -64,23 +271,27 @@ mod tests {
     use std::collections::HashMap;
 
     use super::*;
-    use crate::variables::{MockExtractor, MockInserter};
+    use crate::generators::mutators::GeneratorMutator;
+    use crate::variables::{lexer::tokenize, MockExtractor, MockInserter};
 
     fn setup_extractor(call_times: usize) -> MockExtractor {
         let mut extractor = MockExtractor::new();
+        extractor.expect_missing_from().returning(|| None);
         extractor
             .expect_extract()
             .times(call_times)
-            .returning(|input| vec![input.replace("{{", "").replace("}}", "")]);
+            .returning(|input| {
+                Ok(vec![Placeholder::new(
+                    &input.replace("{{", "").replace("}}", ""),
+                )])
+            });
         extractor
     }
 
-    fn setup_inserter(call_times: usize) -> MockInserter {
+    fn setup_inserter(opening: &str, expands: bool) -> MockInserter {
         let mut inserter = MockInserter::new();
-        inserter
-            .expect_insert()
-            .times(call_times)
-            .returning(|_, _| "replaced".to_string());
+        inserter.expect_opening().return_const(opening.to_string());
+        inserter.expect_expands().return_const(expands);
         inserter
     }
 
@@ -91,25 +302,166 @@ mod tests {
         builder.add_extractor(&mock_extractor);
 
         let user_args = vec!["{{key}}".to_string(), "{{value}}".to_string()];
-        let keys = builder.extract(&user_args);
+        let keys = builder.extract(&user_args).unwrap();
+
+        assert_eq!(
+            keys,
+            vec![Placeholder::new("key"), Placeholder::new("value")]
+        );
+    }
+
+    #[test]
+    fn extract_should_report_failing_argument() {
+        let mut extractor = MockExtractor::new();
+        extractor
+            .expect_extract()
+            .returning(|input| tokenize(input, "r{", "}").map(|_| vec![]));
+        extractor.expect_missing_from().returning(|| None);
+        let mut builder = VariablesBuilder::new();
+        builder.add_extractor(&extractor);
+
+        let error = builder
+            .extract(&["-X".to_string(), "r{method".to_string()])
+            .unwrap_err();
 
-        assert_eq!(keys, vec!["key".to_string(), "value".to_string(),]);
+        match error {
+            ExtractError::Template(error) => assert_eq!(error.argument, Some(2)),
+            ExtractError::Unresolved(_) => panic!("expected a template error"),
+        }
+    }
+
+    fn setup_source(opening: &'static str, source: &'static str, can_save: bool) -> MockExtractor {
+        let mut extractor = MockExtractor::new();
+        extractor.expect_extract().returning(move |input| {
+            Ok(input
+                .strip_prefix(opening)
+                .map(|name| vec![Placeholder::new(name.trim_end_matches('}'))])
+                .unwrap_or_default())
+        });
+        extractor
+            .expect_missing_from()
+            .returning(move || Some(source.to_string()));
+        extractor.expect_can_save().returning(move || can_save);
+        extractor
+    }
+
+    #[test]
+    fn extract_should_report_unresolved_placeholders_by_source() {
+        let workspace = setup_source("w{", "workspace", true);
+        let environment = setup_source("e{", "environment", false);
+
+        let mut builder = VariablesBuilder::new();
+        builder.add_extractor(&workspace);
+        builder.add_extractor(&environment);
+
+        let error = builder
+            .extract(&[
+                "w{host}".to_string(),
+                "e{TOKEN}".to_string(),
+                "w{base_url}".to_string(),
+            ])
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Unresolved placeholders:\n  workspace: host, base_url\n  environment: TOKEN"
+        );
+    }
+
+    #[test]
+    fn extract_should_see_expanded_values() {
+        let mut workspace = setup_inserter("w{", true);
+        workspace
+            .expect_value()
+            .returning(|_, _| Some("r{host}/users".to_string()));
+        let prompted = setup_inserter("r{", false);
+        let mut extractor = MockExtractor::new();
+        extractor
+            .expect_extract()
+            .withf(|input| input == "r{host}/users")
+            .returning(|_| Ok(vec![Placeholder::new("host")]));
+        extractor.expect_missing_from().returning(|| None);
+
+        let mut builder = VariablesBuilder::new();
+        builder.add_inserter(&workspace);
+        builder.add_inserter(&prompted);
+        builder.add_extractor(&extractor);
+
+        let keys = builder.extract(&["w{url}".to_string()]).unwrap();
+
+        assert_eq!(keys, vec![Placeholder::new("host")]);
     }
 
     #[test]
     fn cmd_should_insert_values() {
-        let mock_inserter = setup_inserter(2);
+        let mut mock_inserter = setup_inserter("r{", false);
+        mock_inserter
+            .expect_value()
+            .times(2)
+            .returning(|_, _| Some("replaced".to_string()));
 
         let mut builder = VariablesBuilder::new();
         builder.add_inserter(&mock_inserter);
 
         let curld = CurldCommand {
-            user_args: vec!["{{key}}".to_string(), "{{value}}".to_string()],
+            user_args: vec!["r{key}".to_string(), "-d r{value}".to_string()],
             value_map: HashMap::new(),
         };
 
         let cmd = builder.insert(&curld);
 
-        assert_eq!(cmd, vec!["replaced".to_string(), "replaced".to_string()]);
+        assert_eq!(cmd, vec!["replaced".to_string(), "-d replaced".to_string()]);
+    }
+
+    #[test]
+    fn insert_should_not_read_values_as_templates() {
+        let mut workspace = setup_inserter("w{", true);
+        workspace
+            .expect_value()
+            .returning(|placeholder, _| Some(format!("{}.example.com", placeholder.name)));
+        let mut prompted = setup_inserter("r{", false);
+        prompted
+            .expect_value()
+            .returning(|placeholder, value_map| value_map.get(&placeholder.name).cloned());
+
+        let mut builder = VariablesBuilder::new();
+        builder.add_inserter(&workspace);
+        builder.add_inserter(&prompted);
+
+        let curld = CurldCommand {
+            user_args: vec!["https://w{api}/r{path}".to_string()],
+            value_map: HashMap::from([("path".to_string(), "w{secret}".to_string())]),
+        };
+
+        assert_eq!(
+            builder.insert(&curld),
+            vec!["https://api.example.com/w{secret}".to_string()]
+        );
+    }
+
+    #[test]
+    fn to_string_should_keep_placeholders_without_a_display_value() {
+        let generator = GeneratorMutator {};
+        let mut prompted = setup_inserter("r{", false);
+        prompted
+            .expect_display()
+            .returning(|placeholder, value_map| value_map.get(&placeholder.name).cloned());
+
+        let mut builder = VariablesBuilder::new();
+        builder.add_inserter(&generator);
+        builder.add_inserter(&prompted);
+
+        let curld = CurldCommand {
+            user_args: vec![
+                "n=f{random_int:1:100}&at=f{now:rfc3339}".to_string(),
+                "r{page:5}/r{size:50}".to_string(),
+            ],
+            value_map: HashMap::from([("page".to_string(), "2".to_string())]),
+        };
+
+        assert_eq!(
+            builder.to_string(&curld),
+            "n=f{random_int:1:100}&at=f{now:rfc3339} 2/r{size:50}"
+        );
     }
 }
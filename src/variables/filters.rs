@@ -0,0 +1,108 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+type Apply = fn(&str) -> Result<String, String>;
+
+/// Filters that can follow a placeholder's name, like `r{query|urlencode}`.
+/// New filters only need adding here to be usable in every namespace.
+static FILTERS: &[(&str, Apply)] = &[
+    ("urlencode", urlencode),
+    ("base64", |value| Ok(STANDARD.encode(value))),
+    ("base64_decode", base64_decode),
+    ("json_escape", json_escape),
+    ("trim", |value| Ok(value.trim().to_string())),
+    ("upper", |value| Ok(value.to_uppercase())),
+    ("lower", |value| Ok(value.to_lowercase())),
+    ("sha256", sha256),
+];
+
+pub fn is_filter(name: &str) -> bool {
+    FILTERS.iter().any(|(filter, _)| *filter == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    FILTERS.iter().map(|(name, _)| *name).collect()
+}
+
+/// Runs the value through each filter in turn
+pub fn apply(filters: &[String], value: &str) -> Result<String, String> {
+    filters.iter().try_fold(value.to_string(), |value, name| {
+        let (_, filter) = FILTERS
+            .iter()
+            .find(|(filter, _)| filter == name)
+            .ok_or_else(|| format!("unknown filter {}", name))?;
+
+        filter(&value).map_err(|reason| format!("{} failed: {}", name, reason))
+    })
+}
+
+fn urlencode(value: &str) -> Result<String, String> {
+    Ok(value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect())
+}
+
+fn base64_decode(value: &str) -> Result<String, String> {
+    let bytes = STANDARD
+        .decode(value.trim())
+        .map_err(|error| error.to_string())?;
+
+    String::from_utf8(bytes).map_err(|_| "the decoded value is not text".to_string())
+}
+
+// The value is escaped to go inside a JSON string, so the quotes are left off
+fn json_escape(value: &str) -> Result<String, String> {
+    let quoted = serde_json::to_string(value).map_err(|error| error.to_string())?;
+
+    Ok(quoted[1..quoted.len() - 1].to_string())
+}
+
+fn sha256(value: &str) -> Result<String, String> {
+    Ok(Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(filters: &[&str], value: &str) -> Result<String, String> {
+        let filters: Vec<String> = filters.iter().map(|name| name.to_string()).collect();
+        apply(&filters, value)
+    }
+
+    #[test]
+    fn apply_should_chain_filters() {
+        assert_eq!(run(&["trim", "upper"], "  ada "), Ok("ADA".to_string()));
+        assert_eq!(
+            run(&["urlencode"], "a b&c=d/é"),
+            Ok("a%20b%26c%3Dd%2F%C3%A9".to_string())
+        );
+        assert_eq!(
+            run(&["base64", "base64_decode"], "user:pass"),
+            Ok("user:pass".to_string())
+        );
+        assert_eq!(
+            run(&["json_escape"], "say \"hi\"\n"),
+            Ok(r#"say \"hi\"\n"#.to_string())
+        );
+        assert_eq!(
+            run(&["sha256"], "abc"),
+            Ok("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string())
+        );
+    }
+
+    #[test]
+    fn apply_should_report_failing_filter() {
+        assert!(run(&["base64_decode"], "not base64!").is_err());
+        assert!(run(&["missing"], "value").is_err());
+    }
+}
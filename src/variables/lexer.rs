@@ -0,0 +1,440 @@
+use std::fmt;
+
+use super::{filters, placeholder::Placeholder};
+
+// How much of the argument is shown either side of the error
+static EXCERPT_RADIUS: usize = 30;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
+    Literal(String),
+    Placeholder {
+        // The opener the placeholder was written with, like `r{`
+        opening: String,
+        placeholder: Placeholder,
+        column: usize,
+    },
+}
+
+/// A template that couldn't be read, pointing at where in the argument it went wrong
+#[derive(Debug, PartialEq, Clone)]
+pub struct TemplateError {
+    // 1-based position of the argument, when the template came from one
+    pub argument: Option<usize>,
+    // 1-based character column within the argument
+    pub column: usize,
+    pub message: String,
+    template: String,
+}
+
+impl TemplateError {
+    fn new(template: &str, byte_index: usize, message: String) -> Self {
+        Self {
+            argument: None,
+            column: template[..byte_index].chars().count() + 1,
+            message,
+            template: template.to_string(),
+        }
+    }
+
+    /// An error about a placeholder that was read correctly but can't be used
+    pub fn at_column(template: &str, column: usize, message: String) -> Self {
+        Self {
+            argument: None,
+            column,
+            message,
+            template: template.to_string(),
+        }
+    }
+
+    pub fn in_argument(mut self, argument: usize) -> Self {
+        self.argument = Some(argument);
+        self
+    }
+
+    /// The part of the template around the error with a caret under it
+    pub fn excerpt(&self) -> String {
+        // Newlines and tabs in JSON bodies would throw the caret out of line
+        let chars: Vec<char> = self
+            .template
+            .chars()
+            .map(|c| if c.is_whitespace() { ' ' } else { c })
+            .collect();
+
+        let position = self.column - 1;
+        let start = position.saturating_sub(EXCERPT_RADIUS);
+        let end = chars.len().min(position + EXCERPT_RADIUS);
+
+        let prefix = if start > 0 { "..." } else { "" };
+        let suffix = if end < chars.len() { "..." } else { "" };
+        let shown: String = chars[start..end].iter().collect();
+
+        format!(
+            "  {}{}{}\n  {}^",
+            prefix,
+            shown,
+            suffix,
+            " ".repeat(prefix.len() + position - start)
+        )
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.argument {
+            Some(argument) => write!(
+                f,
+                "Template error in argument {}, column {}: {}",
+                argument, self.column, self.message
+            )?,
+            None => write!(
+                f,
+                "Template error at column {}: {}",
+                self.column, self.message
+            )?,
+        }
+
+        write!(f, "\n{}", self.excerpt())
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Splits a template into literal text and placeholders such as `r{name}`.
+/// Doubling the opener's last character, like `r{{`, writes the opener as literal text.
+pub fn tokenize(template: &str, opening: &str, closing: &str) -> Result<Vec<Token>, TemplateError> {
+    tokenize_namespaces(template, &[opening], closing)
+}
+
+/// Splits a template into literal text and the placeholders of every namespace in one pass
+pub fn tokenize_namespaces(
+    template: &str,
+    openings: &[&str],
+    closing: &str,
+) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut index = 0;
+
+    let escapes: Vec<(&str, String)> = openings
+        .iter()
+        .map(|opening| match opening.chars().last() {
+            Some(last) => (*opening, format!("{}{}", opening, last)),
+            None => (*opening, opening.to_string()),
+        })
+        .collect();
+
+    while index < template.len() {
+        if let Some((opening, escaped)) = escapes
+            .iter()
+            .find(|(_, escaped)| template[index..].starts_with(escaped.as_str()))
+        {
+            literal.push_str(opening);
+            index += escaped.len();
+            continue;
+        }
+
+        if let Some(opening) = openings
+            .iter()
+            .find(|opening| opens_placeholder(template, index, opening))
+        {
+            let name_start = index + opening.len();
+            let close = template[name_start..]
+                .find(closing)
+                .map(|offset| name_start + offset);
+            let nested = openings
+                .iter()
+                .filter_map(|inner| {
+                    let offset = template[name_start..]
+                        .match_indices(inner)
+                        .map(|(offset, _)| offset)
+                        .find(|offset| opens_placeholder(template, name_start + offset, inner))?;
+                    Some((name_start + offset, *inner))
+                })
+                .min_by_key(|(nested, _)| *nested);
+
+            let name_end = match (close, nested) {
+                (None, _) => {
+                    return Err(TemplateError::new(
+                        template,
+                        index,
+                        format!("`{}` is never closed with `{}`", opening, closing),
+                    ))
+                }
+                (Some(close), Some((nested, inner))) if nested < close => {
+                    return Err(TemplateError::new(
+                        template,
+                        nested,
+                        format!(
+                            "`{}` opened inside another placeholder, expected `{}` first",
+                            inner, closing
+                        ),
+                    ))
+                }
+                (Some(close), _) => close,
+            };
+
+            let body = &template[name_start..name_end];
+            // Catches an opener whose closing brace is missing inside JSON or a longer string
+            if let Some((offset, unexpected)) = body
+                .char_indices()
+                .take_while(|(_, c)| *c != ':' && *c != '|')
+                .find(|(_, c)| c.is_whitespace() || "\"'[]{}".contains(*c))
+            {
+                let unexpected = match unexpected.is_whitespace() {
+                    true => "whitespace".to_string(),
+                    false => format!("`{}`", unexpected),
+                };
+                return Err(TemplateError::new(
+                    template,
+                    name_start + offset,
+                    format!(
+                        "unexpected {} in placeholder name, is `{}` missing?",
+                        unexpected, closing
+                    ),
+                ));
+            }
+
+            let placeholder = Placeholder::parse(body);
+
+            // Known filters are taken off while parsing, so a `|` left in the name is a typo
+            if let Some(offset) = placeholder.name.find('|') {
+                let unknown = placeholder.name[offset + 1..]
+                    .split('|')
+                    .next()
+                    .unwrap_or("");
+                return Err(TemplateError::new(
+                    template,
+                    name_start + offset,
+                    format!(
+                        "unknown filter `{}`, expected one of {}",
+                        unknown,
+                        filters::names().join(", ")
+                    ),
+                ));
+            }
+
+            // Anything after `||` is read as a filter, so unknown ones are typos too
+            if let Some(unknown) = placeholder
+                .filters
+                .iter()
+                .find(|filter| !filters::is_filter(filter))
+            {
+                let filters_start = body.find("||").map_or(0, |start| start + 2);
+                let offset =
+                    filters_start + body[filters_start..].find(unknown.as_str()).unwrap_or(0);
+                return Err(TemplateError::new(
+                    template,
+                    name_start + offset,
+                    format!(
+                        "unknown filter `{}`, expected one of {}",
+                        unknown,
+                        filters::names().join(", ")
+                    ),
+                ));
+            }
+
+            if !literal.is_empty() {
+                tokens.push(Token::Literal(std::mem::take(&mut literal)));
+            }
+            tokens.push(Token::Placeholder {
+                opening: opening.to_string(),
+                placeholder,
+                column: template[..index].chars().count() + 1,
+            });
+
+            index = name_end + closing.len();
+            continue;
+        }
+
+        let next = template[index..]
+            .chars()
+            .next()
+            .expect("index is within the template");
+        literal.push(next);
+        index += next.len_utf8();
+    }
+
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+
+    Ok(tokens)
+}
+
+// An opener only starts a placeholder when a name follows it and it isn't the end of a
+// longer word, so braces in JSON or GraphQL bodies, like `node{ id }` or `items{id}`,
+// are left as they are
+fn opens_placeholder(template: &str, index: usize, opening: &str) -> bool {
+    let is_name = |character: char| character.is_alphanumeric() || character == '_';
+    let inside_word = template[..index].chars().last().is_some_and(is_name);
+
+    !inside_word
+        && template[index..]
+            .strip_prefix(opening)
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(is_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_should_split_literals_and_placeholders() {
+        let tokens = tokenize("https://r{host}/é/r{id}", "r{", "}").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Literal("https://".to_string()),
+                Token::Placeholder {
+                    opening: "r{".to_string(),
+                    placeholder: Placeholder::new("host"),
+                    column: 9
+                },
+                Token::Literal("/é/".to_string()),
+                Token::Placeholder {
+                    opening: "r{".to_string(),
+                    placeholder: Placeholder::new("id"),
+                    column: 19
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_should_unescape_doubled_opener() {
+        let tokens = tokenize("r{{literal} r{id}", "r{", "}").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Literal("r{literal} ".to_string()),
+                Token::Placeholder {
+                    opening: "r{".to_string(),
+                    placeholder: Placeholder::new("id"),
+                    column: 13
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_namespaces_should_read_every_opener() {
+        let tokens = tokenize_namespaces("w{host}/r{id}?e{{x}", &["r{", "w{", "e{"], "}").unwrap();
+
+        let openings: Vec<&str> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Placeholder { opening, .. } => Some(opening.as_str()),
+                Token::Literal(_) => None,
+            })
+            .collect();
+        assert_eq!(openings, vec!["w{", "r{"]);
+        assert_eq!(tokens.last(), Some(&Token::Literal("?e{x}".to_string())));
+    }
+
+    #[test]
+    fn tokenize_should_leave_openers_without_a_name_as_text() {
+        let body = r#"{"query": "{ node{ id } e{ name } }", "vars": {"id": "r{id}"}}"#;
+
+        let tokens = tokenize_namespaces(body, &["r{", "e{", "s{", "f{"], "}").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Literal(
+                    r#"{"query": "{ node{ id } e{ name } }", "vars": {"id": ""#.to_string()
+                ),
+                Token::Placeholder {
+                    opening: "r{".to_string(),
+                    placeholder: Placeholder::new("id"),
+                    column: 55,
+                },
+                Token::Literal(r#""}}"#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_should_leave_openers_inside_words_as_text() {
+        let body = r#"{"query": "{ items{id} profile{name} }", "id": "r{id}"}"#;
+
+        let tokens = tokenize_namespaces(body, &["r{", "e{", "s{", "f{"], "}").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Literal(r#"{"query": "{ items{id} profile{name} }", "id": ""#.to_string()),
+                Token::Placeholder {
+                    opening: "r{".to_string(),
+                    placeholder: Placeholder::new("id"),
+                    column: 49,
+                },
+                Token::Literal(r#""}"#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_should_point_at_unclosed_opener() {
+        let error = tokenize("https://r{host/v1", "r{", "}").unwrap_err();
+
+        assert_eq!(error.column, 9);
+        assert_eq!(
+            error.in_argument(2).to_string(),
+            "Template error in argument 2, column 9: `r{` is never closed with `}`\n  https://r{host/v1\n          ^"
+        );
+    }
+
+    #[test]
+    fn tokenize_should_reject_unclosed_placeholder_inside_json() {
+        let error = tokenize(r#"{"user": "r{user", "id": 1}"#, "r{", "}").unwrap_err();
+
+        assert_eq!(error.column, 17);
+    }
+
+    #[test]
+    fn tokenize_should_point_at_nested_opener() {
+        let error = tokenize("r{base r{id}", "r{", "}").unwrap_err();
+
+        assert_eq!(error.column, 8);
+    }
+
+    #[test]
+    fn tokenize_should_point_at_unknown_filter() {
+        let error = tokenize("q=r{query|urlencod}", "r{", "}").unwrap_err();
+
+        assert_eq!(error.column, 10);
+        assert!(error.message.starts_with("unknown filter `urlencod`"));
+    }
+
+    #[test]
+    fn tokenize_should_check_filters_after_double_bar() {
+        let tokens = tokenize("r{case:upper|lower||}", "r{", "}").unwrap();
+        match &tokens[0] {
+            Token::Placeholder { placeholder, .. } => {
+                assert_eq!(placeholder.choices, vec!["upper", "lower"])
+            }
+            token => panic!("expected a placeholder, got {:?}", token),
+        }
+
+        let error = tokenize("r{case:upper|lower||trim|uper}", "r{", "}").unwrap_err();
+
+        assert_eq!(error.column, 26);
+        assert!(error.message.starts_with("unknown filter `uper`"));
+    }
+
+    #[test]
+    fn excerpt_should_trim_long_templates() {
+        let template = format!("{}r{{oops{}", "a/".repeat(25), "b".repeat(50));
+
+        let excerpt = tokenize(&template, "r{", "}").unwrap_err().excerpt();
+        let lines: Vec<&str> = excerpt.lines().collect();
+
+        assert!(lines[0].starts_with("  ..."));
+        assert!(lines[0].ends_with("..."));
+        assert_eq!(lines[0].find("r{"), lines[1].find('^'));
+    }
+}
@@ -4,14 +4,66 @@ use mockall::*;
 use std::collections::HashMap;
 
 pub mod builder;
+pub mod filters;
+pub mod lexer;
 pub mod parse;
+pub mod placeholder;
+pub mod template;
+
+use lexer::TemplateError;
+use placeholder::Placeholder;
 
 #[cfg_attr(test, automock)]
 pub trait Inserter {
-    fn insert(&self, template: &str, value_map: &HashMap<String, String>) -> String;
+    /// The opener of the placeholders this fills in, like `r{`
+    fn opening(&self) -> &str;
+
+    /// The value for a placeholder, or nothing to leave it as it is written
+    fn value(
+        &self,
+        placeholder: &Placeholder,
+        value_map: &HashMap<String, String>,
+    ) -> Option<String>;
+
+    /// How a placeholder is shown, such as in the history, rather than what is sent
+    fn display(
+        &self,
+        placeholder: &Placeholder,
+        value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        self.value(placeholder, value_map)
+    }
+
+    /// Whether values are templates themselves, like workspace variables built from other
+    /// variables. Any other value is inserted exactly as it is.
+    fn expands(&self) -> bool {
+        false
+    }
 }
 
 #[cfg_attr(test, automock)]
 pub trait Extractor {
-    fn extract(&self, template: &str) -> Vec<String>;
+    fn extract(&self, template: &str) -> Result<Vec<Placeholder>, TemplateError>;
+
+    /// Names where the values come from, like the workspace, for extractors that return
+    /// the placeholders missing a value rather than ones to prompt for
+    fn missing_from(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether a missing value can be prompted for and kept with [`Extractor::save`]
+    fn can_save(&self) -> bool {
+        false
+    }
+
+    /// Fills in values the extractor keeps itself, once the other values have been prompted
+    /// for. `given` holds the values given up front.
+    fn fill(&self, _given: &HashMap<String, String>) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Fails when the value can't be kept, such as one that would make values refer to each other
+    fn save(&self, _name: &str, _value: &str) -> Result<(), String> {
+        Ok(())
+    }
 }
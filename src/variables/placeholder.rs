@@ -0,0 +1,405 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::Value;
+
+use super::filters;
+
+/// A placeholder read from a template, like `r{page_size:50}`, `r{id:int}`,
+/// `r{env:dev|staging|prod}` or `r{query|urlencode}`
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Placeholder {
+    pub name: String,
+    pub kind: Option<ValueType>,
+    pub default: Option<String>,
+    // The only values that are accepted, picked from a list when prompting
+    pub choices: Vec<String>,
+    // Applied in order to the value before it is inserted
+    pub filters: Vec<String>,
+}
+
+/// The kind of value a placeholder accepts
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ValueType {
+    Int,
+    Float,
+    Bool,
+    Iso8601,
+    Json,
+}
+
+impl ValueType {
+    pub fn parse(keyword: &str) -> Option<Self> {
+        match keyword.trim() {
+            "int" => Some(ValueType::Int),
+            "float" => Some(ValueType::Float),
+            "bool" => Some(ValueType::Bool),
+            "iso8601" => Some(ValueType::Iso8601),
+            "json" => Some(ValueType::Json),
+            _ => None,
+        }
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Bool => "bool",
+            ValueType::Iso8601 => "iso8601",
+            ValueType::Json => "json",
+        }
+    }
+
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let check = |valid: bool, expected: &str| match valid {
+            true => Ok(()),
+            false => Err(expected.to_string()),
+        };
+
+        match self {
+            ValueType::Int => check(value.parse::<i64>().is_ok(), "expected a whole number"),
+            ValueType::Float => check(value.parse::<f64>().is_ok(), "expected a number"),
+            ValueType::Bool => check(
+                value == "true" || value == "false",
+                "expected true or false",
+            ),
+            ValueType::Iso8601 => check(
+                DateTime::parse_from_rfc3339(value).is_ok()
+                    || NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").is_ok()
+                    || NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+                "expected an ISO 8601 date or time, like 2024-01-31 or 2024-01-31T09:30:00Z",
+            ),
+            ValueType::Json => serde_json::from_str::<Value>(value)
+                .map(|_| ())
+                .map_err(|error| format!("expected JSON: {}", error)),
+        }
+    }
+}
+
+impl Placeholder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Parses what is between the braces, `name`, `name:default`, `name:type`,
+    /// `name:type:default` or `name:one|two`, followed by any filters like `|trim|upper`.
+    /// Text after the colon is only a type when it is a known one, and trailing `|`
+    /// segments are only filters when they are known ones. `||` ends the definition
+    /// instead, so `case:upper|lower||` is a choice of `upper` or `lower`, and only what
+    /// follows it is read as filters.
+    pub fn parse(body: &str) -> Self {
+        if let Some((definition, filters)) = body.split_once("||") {
+            return Self {
+                filters: filters
+                    .split('|')
+                    .map(str::trim)
+                    .filter(|filter| !filter.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                ..Placeholder::parse_definition(definition)
+            };
+        }
+
+        let mut segments: Vec<&str> = body.split('|').collect();
+        let mut filters = Vec::new();
+        while segments.len() > 1 && filters::is_filter(segments[segments.len() - 1].trim()) {
+            let filter = segments.pop().expect("there is more than one segment");
+            filters.insert(0, filter.trim().to_string());
+        }
+
+        Self {
+            filters,
+            ..Placeholder::parse_definition(&segments.join("|"))
+        }
+    }
+
+    fn parse_definition(body: &str) -> Self {
+        let (name, rest) = match body.split_once(':') {
+            Some((name, rest)) => (name, rest),
+            None => return Placeholder::new(body.trim()),
+        };
+
+        let (keyword, default) = match rest.split_once(':') {
+            Some((keyword, default)) => (keyword, Some(default)),
+            None => (rest, None),
+        };
+
+        match ValueType::parse(keyword) {
+            Some(kind) => Self {
+                name: name.trim().to_string(),
+                kind: Some(kind),
+                default: default.map(|default| default.to_string()),
+                ..Default::default()
+            },
+            None if rest.contains('|') => Self {
+                name: name.trim().to_string(),
+                choices: rest.split('|').map(|choice| choice.to_string()).collect(),
+                ..Default::default()
+            },
+            None => Self {
+                name: name.trim().to_string(),
+                default: Some(rest.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The placeholder as it was written, without its opener and closer
+    pub fn body(&self) -> String {
+        let mut body = self.definition();
+        for filter in &self.filters {
+            body = format!("{}|{}", body, filter);
+        }
+
+        // Choices named like filters would be read as filters without `||`
+        match Placeholder::parse(&body) == *self {
+            true => body,
+            false => format!("{}||{}", self.definition(), self.filters.join("|")),
+        }
+    }
+
+    /// The placeholder as it was written, without its filters
+    pub fn definition(&self) -> String {
+        let mut body = self.name.to_owned();
+        if let Some(kind) = self.kind {
+            body = format!("{}:{}", body, kind.keyword());
+        }
+        if let Some(default) = &self.default {
+            body = format!("{}:{}", body, default);
+        }
+        if !self.choices.is_empty() {
+            body = format!("{}:{}", body, self.choices.join("|"));
+        }
+
+        body
+    }
+
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!("Enter value for {}", self.name);
+        if !self.choices.is_empty() {
+            prompt = format!("{} ({})", prompt, self.choices.join("|"));
+        }
+        if let Some(kind) = self.kind {
+            prompt = format!("{} ({})", prompt, kind.keyword());
+        }
+        if let Some(default) = &self.default {
+            prompt = format!("{} [{}]", prompt, default);
+        }
+
+        format!("{}: ", prompt)
+    }
+
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        if !self.choices.is_empty() && !self.choices.iter().any(|choice| choice == value) {
+            return Err(format!(
+                "Invalid value for {}: expected one of {}",
+                self.name,
+                self.choices.join(", ")
+            ));
+        }
+
+        if let Some(kind) = self.kind {
+            kind.validate(value)
+                .map_err(|reason| format!("Invalid value for {}: {}", self.name, reason))?;
+        }
+
+        self.apply_filters(value).map(|_| ())
+    }
+
+    pub fn apply_filters(&self, value: &str) -> Result<String, String> {
+        filters::apply(&self.filters, value)
+            .map_err(|reason| format!("Invalid value for {}: {}", self.name, reason))
+    }
+
+    /// What was typed at the prompt, or the default when nothing was
+    pub fn answer(&self, input: String) -> String {
+        match (&self.default, input.is_empty()) {
+            (Some(default), true) => default.to_owned(),
+            _ => input,
+        }
+    }
+
+    /// Offers a value entered before as the default, in place of the one written
+    pub fn remembering(mut self, value: Option<&String>) -> Self {
+        if let Some(value) = value {
+            self.default = Some(value.to_owned());
+        }
+        self
+    }
+
+    /// The names of the placeholders as a list, like `host, token`
+    pub fn names(placeholders: &[Placeholder]) -> String {
+        placeholders
+            .iter()
+            .map(|placeholder| placeholder.name.to_owned())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Keeps one placeholder per name, in the order they first appear, along with the
+    /// first type, default and choices given for it
+    pub fn dedupe(placeholders: Vec<Placeholder>) -> Vec<Placeholder> {
+        let mut unique: Vec<Placeholder> = Vec::new();
+        for placeholder in placeholders {
+            match unique
+                .iter_mut()
+                .find(|existing| existing.name == placeholder.name)
+            {
+                Some(existing) => {
+                    existing.kind = existing.kind.or(placeholder.kind);
+                    if existing.default.is_none() {
+                        existing.default = placeholder.default;
+                    }
+                    if existing.choices.is_empty() {
+                        existing.choices = placeholder.choices;
+                    }
+                }
+                None => unique.push(placeholder),
+            }
+        }
+
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_should_split_default() {
+        assert_eq!(
+            Placeholder::parse("page_size:50"),
+            Placeholder {
+                name: "page_size".to_string(),
+                default: Some("50".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            Placeholder::parse("url:http://localhost:80").default,
+            Some("http://localhost:80".to_string())
+        );
+        assert_eq!(Placeholder::parse("id"), Placeholder::new("id"));
+    }
+
+    #[test]
+    fn parse_should_read_type_and_default() {
+        assert_eq!(
+            Placeholder::parse("id:int:5"),
+            Placeholder {
+                name: "id".to_string(),
+                kind: Some(ValueType::Int),
+                default: Some("5".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(Placeholder::parse("body:json").kind, Some(ValueType::Json));
+        assert_eq!(Placeholder::parse("body:json").default, None);
+        assert_eq!(Placeholder::parse("id:int:5").body(), "id:int:5");
+    }
+
+    #[test]
+    fn parse_should_read_choices() {
+        let placeholder = Placeholder::parse("env:dev|staging|prod");
+
+        assert_eq!(placeholder.choices, vec!["dev", "staging", "prod"]);
+        assert_eq!(placeholder.default, None);
+        assert!(placeholder.validate("staging").is_ok());
+        assert!(placeholder.validate("qa").is_err());
+        assert_eq!(placeholder.body(), "env:dev|staging|prod");
+    }
+
+    #[test]
+    fn parse_should_read_trailing_filters() {
+        let placeholder = Placeholder::parse("env:dev|prod|trim|upper");
+
+        assert_eq!(placeholder.choices, vec!["dev", "prod"]);
+        assert_eq!(placeholder.filters, vec!["trim", "upper"]);
+        assert_eq!(placeholder.apply_filters(" dev "), Ok("DEV".to_string()));
+        assert_eq!(placeholder.body(), "env:dev|prod|trim|upper");
+
+        let placeholder = Placeholder::parse("query|urlencode");
+
+        assert_eq!(placeholder.name, "query");
+        assert_eq!(placeholder.filters, vec!["urlencode"]);
+    }
+
+    #[test]
+    fn parse_should_end_the_definition_at_double_bar() {
+        let placeholder = Placeholder::parse("case:upper|lower");
+
+        assert_eq!(placeholder.default, Some("upper".to_string()));
+        assert_eq!(placeholder.filters, vec!["lower"]);
+
+        let placeholder = Placeholder::parse("case:upper|lower||");
+
+        assert_eq!(placeholder.choices, vec!["upper", "lower"]);
+        assert!(placeholder.filters.is_empty());
+        assert_eq!(placeholder.body(), "case:upper|lower||");
+
+        let placeholder = Placeholder::parse("case:upper|lower||trim|base64");
+
+        assert_eq!(placeholder.choices, vec!["upper", "lower"]);
+        assert_eq!(placeholder.filters, vec!["trim", "base64"]);
+        assert_eq!(placeholder.body(), "case:upper|lower||trim|base64");
+    }
+
+    #[test]
+    fn validate_should_check_types() {
+        let check = |body: &str, value: &str| Placeholder::parse(body).validate(value).is_ok();
+
+        assert!(check("id:int", "42"));
+        assert!(!check("id:int", "4.2"));
+        assert!(check("ratio:float", "4.2"));
+        assert!(check("active:bool", "false"));
+        assert!(!check("active:bool", "yes"));
+        assert!(check("when:iso8601", "2024-01-31"));
+        assert!(check("when:iso8601", "2024-01-31T09:30:00+01:00"));
+        assert!(!check("when:iso8601", "31/01/2024"));
+        assert!(check("body:json", r#"{ "a": [1, 2] }"#));
+        assert!(!check("body:json", r#"{ "a": "#));
+        assert!(check("anything", "goes"));
+    }
+
+    #[test]
+    fn answer_should_fall_back_to_default() {
+        let placeholder = Placeholder::parse("page_size:50");
+
+        assert_eq!(placeholder.answer(String::new()), "50");
+        assert_eq!(placeholder.answer("10".to_string()), "10");
+        assert_eq!(Placeholder::new("id").answer(String::new()), "");
+    }
+
+    #[test]
+    fn remembered_value_should_replace_default() {
+        let placeholder =
+            Placeholder::parse("page_size:int:50").remembering(Some(&"10".to_string()));
+
+        assert_eq!(
+            placeholder.prompt(),
+            "Enter value for page_size (int) [10]: "
+        );
+        assert_eq!(placeholder.answer(String::new()), "10");
+        assert_eq!(
+            Placeholder::parse("page:1").remembering(None).default,
+            Some("1".to_string())
+        );
+    }
+
+    #[test]
+    fn dedupe_should_keep_first_default() {
+        let placeholders = vec![
+            Placeholder::parse("id"),
+            Placeholder::parse("size:10"),
+            Placeholder::parse("id:1"),
+            Placeholder::parse("size:20"),
+        ];
+
+        assert_eq!(
+            Placeholder::dedupe(placeholders),
+            vec![Placeholder::parse("id:1"), Placeholder::parse("size:10")]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use super::{
+    lexer::{tokenize_namespaces, TemplateError, Token},
+    placeholder::Placeholder,
+};
+
+/// An argument read once into literal text and placeholders, so filling it in is a single
+/// scan and the values inserted are never read as templates again
+#[derive(Debug, PartialEq, Clone)]
+pub struct Template {
+    // The text the template was compiled from, for pointing at placeholders in errors
+    source: String,
+    segments: Vec<Segment>,
+    openings: Vec<String>,
+    closing: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Segment {
+    Literal(String),
+    Placeholder {
+        opening: String,
+        placeholder: Placeholder,
+        column: usize,
+    },
+    // A placeholder whose value is a template itself, like a workspace variable built from others
+    Nested {
+        placeholder: Placeholder,
+        template: Template,
+    },
+}
+
+impl Template {
+    pub fn compile(
+        template: &str,
+        openings: &[&str],
+        closing: &str,
+    ) -> Result<Self, TemplateError> {
+        let segments = tokenize_namespaces(template, openings, closing)?
+            .into_iter()
+            .map(|token| match token {
+                Token::Literal(text) => Segment::Literal(text),
+                Token::Placeholder {
+                    opening,
+                    placeholder,
+                    column,
+                } => Segment::Placeholder {
+                    opening,
+                    placeholder,
+                    column,
+                },
+            })
+            .collect();
+
+        Ok(Self {
+            source: template.to_string(),
+            segments,
+            openings: openings.iter().map(|opening| opening.to_string()).collect(),
+            closing: closing.to_string(),
+        })
+    }
+
+    /// Replaces placeholders whose values are templates with those templates, expanded in
+    /// turn. Values that lead back to a placeholder already being expanded are an error
+    /// naming the chain, pointing at where it starts.
+    pub fn expand(
+        self,
+        source: &dyn Fn(&str, &Placeholder) -> Option<String>,
+    ) -> Result<Self, TemplateError> {
+        let template = self.source.clone();
+        let mut column = 0;
+        self.expand_within(source, &mut Vec::new(), &mut column)
+            .map_err(|message| TemplateError::at_column(&template, column, message))
+    }
+
+    // `column` is set to the column of the outermost placeholder being expanded
+    fn expand_within(
+        mut self,
+        source: &dyn Fn(&str, &Placeholder) -> Option<String>,
+        chain: &mut Vec<String>,
+        column: &mut usize,
+    ) -> Result<Self, String> {
+        let openings: Vec<&str> = self.openings.iter().map(String::as_str).collect();
+        let mut segments = Vec::new();
+        for segment in std::mem::take(&mut self.segments) {
+            let (opening, placeholder, at) = match &segment {
+                Segment::Placeholder {
+                    opening,
+                    placeholder,
+                    column,
+                } => (opening, placeholder, *column),
+                _ => {
+                    segments.push(segment);
+                    continue;
+                }
+            };
+
+            if chain.is_empty() {
+                *column = at;
+            }
+            let key = format!("{}{}{}", opening, placeholder.name, self.closing);
+            if chain.contains(&key) {
+                let start = chain.iter().position(|seen| *seen == key).unwrap_or(0);
+                let mut cycle = chain[start..].to_vec();
+                cycle.push(key);
+                return Err(format!("values refer to each other: {}", cycle.join(" → ")));
+            }
+
+            // A value that isn't a valid template is inserted as it is
+            let nested = source(opening, placeholder)
+                .and_then(|value| Template::compile(&value, &openings, &self.closing).ok());
+            match nested {
+                Some(template) => {
+                    chain.push(key);
+                    let template = template.expand_within(source, chain, column)?;
+                    chain.pop();
+
+                    segments.push(Segment::Nested {
+                        placeholder: placeholder.to_owned(),
+                        template,
+                    });
+                }
+                None => segments.push(segment),
+            }
+        }
+
+        self.segments = segments;
+        Ok(self)
+    }
+
+    /// Fills in every placeholder in one pass. Placeholders without a value are left as they
+    /// were written, so `value` decides whether a default is used.
+    pub fn render(&self, value: &dyn Fn(&str, &Placeholder) -> Option<String>) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.to_owned(),
+                Segment::Placeholder {
+                    opening,
+                    placeholder,
+                    ..
+                } => match value(opening, placeholder) {
+                    Some(value) => filter(placeholder, value),
+                    None => format!("{}{}{}", opening, placeholder.body(), self.closing),
+                },
+                Segment::Nested {
+                    placeholder,
+                    template,
+                } => filter(placeholder, template.render(value)),
+            })
+            .collect()
+    }
+
+    /// The template as it would be written, with nested templates in place of their placeholders
+    pub fn written(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => self.escape(text),
+                Segment::Placeholder {
+                    opening,
+                    placeholder,
+                    ..
+                } => format!("{}{}{}", opening, placeholder.body(), self.closing),
+                Segment::Nested { template, .. } => template.written(),
+            })
+            .collect()
+    }
+
+    // Literal text holding an opener was written escaped, and needs to stay that way
+    fn escape(&self, text: &str) -> String {
+        let openings: Vec<&str> = self.openings.iter().map(String::as_str).collect();
+        escape(text, &openings)
+    }
+}
+
+/// Doubles the brace of every opener in the text, so it is read back as literal text
+pub fn escape(text: &str, openings: &[&str]) -> String {
+    let mut escaped = String::new();
+    let mut index = 0;
+    while index < text.len() {
+        match openings
+            .iter()
+            .find(|opening| text[index..].starts_with(**opening))
+        {
+            Some(opening) => {
+                escaped.push_str(opening);
+                escaped.extend(opening.chars().last());
+                index += opening.len();
+            }
+            None => {
+                let next = text[index..]
+                    .chars()
+                    .next()
+                    .expect("index is within the text");
+                escaped.push(next);
+                index += next.len_utf8();
+            }
+        }
+    }
+
+    escaped
+}
+
+// Values are checked before this point, so a failing filter leaves the value as it is
+fn filter(placeholder: &Placeholder, value: String) -> String {
+    placeholder.apply_filters(&value).unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    static OPENINGS: &[&str] = &["${", "$w{"];
+
+    fn fill(template: &str, values: &[(&str, &str)]) -> String {
+        let value_map: HashMap<String, String> = values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        Template::compile(template, OPENINGS, "}")
+            .unwrap()
+            .render(&|_, placeholder| {
+                value_map
+                    .get(&placeholder.name)
+                    .cloned()
+                    .or_else(|| placeholder.default.clone())
+            })
+    }
+
+    #[test]
+    fn render_should_insert_values() {
+        assert_eq!(
+            fill(
+                "https://${base_url}/v1/${resource}/${resouceId}",
+                &[
+                    ("base_url", "something.com"),
+                    ("resource", "user"),
+                    ("resouceId", "uuid")
+                ]
+            ),
+            "https://something.com/v1/user/uuid"
+        );
+        assert_eq!(
+            fill(
+                r#"{ "one": "${one_value}", "two": ${two_value} }"#,
+                &[("one_value", "first_value"), ("two_value", "2")]
+            ),
+            r#"{ "one": "first_value", "two": 2 }"#
+        );
+    }
+
+    #[test]
+    fn render_should_fill_every_namespace_in_one_pass() {
+        let template = Template::compile(
+            "-X ${METHOD} https://$w{base_url}/$w{version}",
+            OPENINGS,
+            "}",
+        )
+        .unwrap();
+
+        let rendered = template.render(&|opening, placeholder| match opening {
+            "$w{" => Some(format!("w-{}", placeholder.name)),
+            _ => None,
+        });
+
+        assert_eq!(rendered, "-X ${METHOD} https://w-base_url/w-version");
+    }
+
+    #[test]
+    fn render_should_not_read_values_as_templates() {
+        assert_eq!(
+            fill("${name} $w{user}", &[("name", "$w{user}"), ("user", "ada")]),
+            "$w{user} ada"
+        );
+    }
+
+    #[test]
+    fn escaped_opener_should_be_literal() {
+        assert_eq!(
+            fill(
+                r#"{ "template": "${{name}", "name": "${name}" }"#,
+                &[("name", "ada")]
+            ),
+            r#"{ "template": "${name}", "name": "ada" }"#
+        );
+        assert_eq!(
+            fill("echo $w{{HOME} $w{user} '$w{{x}'", &[("user", "ada")]),
+            "echo $w{HOME} ada '$w{x}'"
+        );
+    }
+
+    #[test]
+    fn defaults_and_filters_should_apply_to_each_occurrence() {
+        assert_eq!(
+            fill(
+                "https://${host}/items?size=${page_size:50}&page=${page:1}",
+                &[("host", "test.com"), ("page", "3")]
+            ),
+            "https://test.com/items?size=50&page=3"
+        );
+        assert_eq!(
+            fill(
+                "https://${host}/search?q=${query|urlencode}&raw=${query|upper}",
+                &[("host", "test.com"), ("query", "a b")]
+            ),
+            "https://test.com/search?q=a%20b&raw=A B"
+        );
+    }
+
+    #[test]
+    fn expand_should_nest_templates() {
+        let variables = HashMap::from([
+            ("api".to_string(), "$w{host}/${version}".to_string()),
+            ("host".to_string(), "example.com".to_string()),
+        ]);
+        let source = |opening: &str, placeholder: &Placeholder| match opening {
+            "$w{" => variables.get(&placeholder.name).cloned(),
+            _ => None,
+        };
+
+        let template = Template::compile("$w{api|upper} $w{host}", OPENINGS, "}")
+            .unwrap()
+            .expand(&source)
+            .unwrap();
+
+        assert_eq!(template.written(), "example.com/${version} example.com");
+        assert_eq!(
+            Template::compile("${{x} ${y}", OPENINGS, "}")
+                .unwrap()
+                .written(),
+            "${{x} ${y}"
+        );
+        assert_eq!(
+            template.render(&|_, placeholder| Some(placeholder.name.to_owned())),
+            "EXAMPLE.COM/VERSION example.com"
+        );
+    }
+
+    #[test]
+    fn expand_should_name_cycles() {
+        let variables = HashMap::from([
+            ("a".to_string(), "$w{b}".to_string()),
+            ("b".to_string(), "${c}/$w{a}".to_string()),
+        ]);
+        let source = |opening: &str, placeholder: &Placeholder| match opening {
+            "$w{" => variables.get(&placeholder.name).cloned(),
+            _ => None,
+        };
+
+        let error = Template::compile("https://$w{a}", OPENINGS, "}")
+            .unwrap()
+            .expand(&source)
+            .unwrap_err();
+
+        assert_eq!(error.column, 9);
+        assert_eq!(
+            error.message,
+            "values refer to each other: $w{a} → $w{b} → $w{a}"
+        );
+    }
+}
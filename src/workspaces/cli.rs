@@ -1,3 +1,5 @@
+use std::process::ExitCode;
+
 use crate::common::IO;
 
 use super::settings::WorkspacesManager;
@@ -5,31 +7,95 @@ use super::settings::WorkspacesManager;
 #[derive(clap::Subcommand, Debug)]
 pub enum WorkspacesCommand {
     List,
-    Create { name: String },
-    Use { name: String },
-    SetVariable { key: String, value: String },
+    Create {
+        name: String,
+    },
+    Use {
+        name: String,
+    },
+    /// Set a variable, picking from its allowed values or prompting when no value is given
+    SetVariable {
+        key: String,
+        value: Option<String>,
+    },
+    /// Only allow a variable to be set to one of the given values, or allow anything when none are given
+    Allow {
+        key: String,
+        values: Vec<String>,
+    },
 }
 
 impl WorkspacesCommand {
-    pub fn cli_match(command: &WorkspacesCommand, workspaces_manager: &mut WorkspacesManager) {
+    pub fn cli_match(
+        command: &WorkspacesCommand,
+        workspaces_manager: &mut WorkspacesManager,
+    ) -> ExitCode {
         match command {
             WorkspacesCommand::List => {
                 let list = workspaces_manager.get_workspaces_names();
                 IO::output(&list.join("\n"));
             }
             WorkspacesCommand::Create { name } => {
-                workspaces_manager.change_workspace(&name);
+                workspaces_manager.change_workspace(name);
                 IO::output(&format!("Workspace created: {}", name));
             }
             WorkspacesCommand::Use { name } => {
-                workspaces_manager.change_workspace(&name);
+                workspaces_manager.change_workspace(name);
                 IO::output(&format!("Workspace changed to {}", name));
             }
             WorkspacesCommand::SetVariable { key, value } => {
-                workspaces_manager.set_variable(key, value);
+                if value.is_none() && !IO::accepts_input() {
+                    IO::error(&format!("No value given for {}", key));
+                    return ExitCode::FAILURE;
+                }
+
+                let allowed = workspaces_manager.get_allowed(key).cloned();
+                let value = match (value, &allowed) {
+                    (Some(value), _) => value.to_owned(),
+                    (None, Some(allowed)) => {
+                        let current = workspaces_manager
+                            .get_current_workspace()
+                            .variables
+                            .get(key)
+                            .and_then(|current| allowed.iter().position(|value| value == current))
+                            .unwrap_or(0);
+                        match IO::select(&format!("Choose a value for {}", key), allowed, current) {
+                            Ok(value) => value,
+                            Err(error) => {
+                                IO::error(&error);
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                    }
+                    (None, None) => IO::prompt(&format!("Enter value for {}: ", key)),
+                };
+
+                if let Some(allowed) = allowed.filter(|allowed| !allowed.contains(&value)) {
+                    IO::error(&format!(
+                        "Invalid value for {}: expected one of {}",
+                        key,
+                        allowed.join(", ")
+                    ));
+                    return ExitCode::FAILURE;
+                }
+
+                if let Err(error) = workspaces_manager.set_variable(key, &value) {
+                    IO::error(&format!("Could not set {}: {}", key, error));
+                    return ExitCode::FAILURE;
+                }
+
                 IO::output(&format!("Variable set: {}={}", key, value));
             }
+            WorkspacesCommand::Allow { key, values } => {
+                workspaces_manager.set_allowed(key, values.to_owned());
+                match values.is_empty() {
+                    true => IO::output(&format!("{} can be set to any value", key)),
+                    false => IO::output(&format!("{} can be set to {}", key, values.join(", "))),
+                }
+            }
         }
+
+        ExitCode::SUCCESS
     }
 }
 
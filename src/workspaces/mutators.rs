@@ -1,47 +1,264 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::variables::{parse, Inserter};
-
-use super::settings::Workspace;
+use crate::variables::{
+    lexer::{tokenize, TemplateError, Token},
+    placeholder::Placeholder,
+    Extractor, Inserter,
+};
 
 static OPENING: &str = "w{";
 static CLOSING: &str = "}";
 
 pub struct WorkspaceMutator {
-    value_map: HashMap<String, String>,
+    value_map: Rc<RefCell<HashMap<String, String>>>,
+    allowed: HashMap<String, Vec<String>>,
 }
 
 impl WorkspaceMutator {
-    pub fn new(workspace: &Workspace) -> WorkspaceMutator {
+    // Sees variables set after it was created, such as values captured from a response
+    pub fn new(value_map: Rc<RefCell<HashMap<String, String>>>) -> WorkspaceMutator {
         WorkspaceMutator {
-            value_map: workspace.variables.clone(),
+            value_map,
+            allowed: HashMap::new(),
         }
     }
+
+    /// Limits the values that can be saved for variables to the given lists
+    pub fn allowing(mut self, allowed: HashMap<String, Vec<String>>) -> WorkspaceMutator {
+        self.allowed = allowed;
+        self
+    }
+
+    // Missing values for variables with allowed values are picked from them
+    fn limited(&self, mut placeholder: Placeholder) -> Placeholder {
+        if let Some(allowed) = self.allowed.get(&placeholder.name) {
+            placeholder.choices = allowed.to_owned();
+        }
+        placeholder
+    }
 }
 
 impl Inserter for WorkspaceMutator {
-    fn insert(&self, template: &str, _value_map: &HashMap<String, String>) -> String {
-        parse::insert_variable_values(template, &self.value_map, OPENING, CLOSING)
+    fn opening(&self) -> &str {
+        OPENING
+    }
+
+    fn value(
+        &self,
+        placeholder: &Placeholder,
+        _value_map: &HashMap<String, String>,
+    ) -> Option<String> {
+        self.value_map.borrow().get(&placeholder.name).cloned()
+    }
+
+    // Workspace values can hold placeholders from any namespace, like
+    // `api_url = w{host}/r{version}`, which are filled in like any other
+    fn expands(&self) -> bool {
+        true
+    }
+}
+
+impl Extractor for WorkspaceMutator {
+    // Runs on the expanded template, so the placeholders left are ones the workspace
+    // has no value for
+    fn extract(&self, template: &str) -> Result<Vec<Placeholder>, TemplateError> {
+        let value_map = self.value_map.borrow();
+        let cycle = check_cycles(&value_map).err();
+
+        let mut missing = Vec::new();
+        for token in tokenize(template, OPENING, CLOSING)? {
+            if let Token::Placeholder {
+                placeholder,
+                column,
+                ..
+            } = token
+            {
+                match (value_map.contains_key(&placeholder.name), &cycle) {
+                    (true, Some(cycle)) => {
+                        return Err(TemplateError::at_column(template, column, cycle.to_owned()))
+                    }
+                    (false, _) if placeholder.default.is_none() => {
+                        missing.push(self.limited(placeholder))
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(missing)
+    }
+
+    fn missing_from(&self) -> Option<String> {
+        Some("workspace".to_string())
+    }
+
+    fn can_save(&self) -> bool {
+        true
+    }
+
+    // Kept with the current variables, which are saved to the workspace once the command is done
+    fn save(&self, name: &str, value: &str) -> Result<(), String> {
+        self.limited(Placeholder::new(name)).validate(value)?;
+
+        let mut variables = self.value_map.borrow().clone();
+        variables.insert(name.to_string(), value.to_string());
+        check_cycles(&variables)?;
+
+        *self.value_map.borrow_mut() = variables;
+        Ok(())
+    }
+}
+
+/// Checks variables that refer to other variables, like `api_url = w{host}/w{version}`,
+/// never lead back to themselves
+pub fn check_cycles(variables: &HashMap<String, String>) -> Result<(), String> {
+    let mut keys: Vec<&String> = variables.keys().collect();
+    keys.sort();
+
+    let mut checked = Vec::new();
+    for key in keys {
+        check_key(key, variables, &mut checked, &mut Vec::new())?;
+    }
+
+    Ok(())
+}
+
+fn check_key(
+    key: &str,
+    variables: &HashMap<String, String>,
+    checked: &mut Vec<String>,
+    chain: &mut Vec<String>,
+) -> Result<(), String> {
+    if checked.iter().any(|done| done == key) {
+        return Ok(());
+    }
+
+    if let Some(start) = chain.iter().position(|seen| seen == key) {
+        let mut cycle = chain[start..].to_vec();
+        cycle.push(key.to_string());
+        return Err(format!(
+            "workspace variables refer to each other: {}",
+            cycle.join(" → ")
+        ));
     }
+
+    let value = &variables[key];
+    chain.push(key.to_string());
+    // A value that isn't a valid template is used as it is written
+    for token in tokenize(value, OPENING, CLOSING).unwrap_or_default() {
+        if let Token::Placeholder { placeholder, .. } = token {
+            if variables.contains_key(&placeholder.name) {
+                check_key(&placeholder.name, variables, checked, chain)?;
+            }
+        }
+    }
+    chain.pop();
+    checked.push(key.to_string());
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        common::CurldCommand, run::mutators::RunMutators, variables::builder::VariablesBuilder,
+    };
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
 
     #[test]
     fn inserter_should_insert_values_for_workspace() {
-        let workspace = Workspace {
-            name: "test".to_string(),
-            variables: vec![("key".to_string(), "value".to_string())]
-                .into_iter()
-                .collect(),
-            commands: vec![],
-        };
-        let mutator = WorkspaceMutator::new(&workspace);
-
-        let result = mutator.insert("test w{key}", &HashMap::new());
-
-        assert_eq!("test value", result);
+        let mutator = WorkspaceMutator::new(Rc::new(RefCell::new(variables(&[("key", "value")]))));
+
+        let result = mutator.value(&Placeholder::new("key"), &HashMap::new());
+
+        assert_eq!(Some("value".to_string()), result);
+    }
+
+    #[test]
+    fn extractor_should_limit_missing_values_to_allowed() {
+        let allowed = vec!["dev".to_string(), "prod".to_string()];
+        let mutator = WorkspaceMutator::new(Rc::new(RefCell::new(HashMap::new())))
+            .allowing(HashMap::from([("env".to_string(), allowed.clone())]));
+
+        let missing = mutator.extract("w{env}").unwrap();
+
+        assert_eq!(missing[0].choices, allowed);
+        assert!(mutator.save("env", "staging").is_err());
+        assert_eq!(mutator.save("env", "prod"), Ok(()));
+    }
+
+    #[test]
+    fn shared_inserter_should_see_later_values() {
+        let value_map = Rc::new(RefCell::new(HashMap::new()));
+        let mutator = WorkspaceMutator::new(Rc::clone(&value_map));
+
+        value_map
+            .borrow_mut()
+            .insert("token".to_string(), "abc".to_string());
+
+        assert_eq!(
+            Some("abc".to_string()),
+            mutator.value(&Placeholder::new("token"), &HashMap::new())
+        );
+    }
+
+    #[test]
+    fn inserter_should_expand_nested_values() {
+        let mutator = WorkspaceMutator::new(Rc::new(RefCell::new(variables(&[
+            ("users_url", "w{api_url}/users?limit=r{limit}"),
+            ("api_url", "w{host}/w{version}"),
+            ("host", "https://example.com"),
+            ("version", "v2"),
+        ]))));
+        let run_mutators = RunMutators {};
+        let mut builder = VariablesBuilder::new();
+        builder.add_inserter(&mutator);
+        builder.add_inserter(&run_mutators);
+
+        let curld = CurldCommand::new(
+            vec!["w{users_url}".to_string()],
+            variables(&[("limit", "w{version}")]),
+        );
+
+        assert_eq!(
+            builder.insert(&curld),
+            vec!["https://example.com/v2/users?limit=w{version}".to_string()]
+        );
+    }
+
+    #[test]
+    fn extractor_should_return_missing_values() {
+        let mutator = WorkspaceMutator::new(Rc::new(RefCell::new(variables(&[(
+            "host",
+            "https://example.com",
+        )]))));
+
+        let missing = mutator.extract("w{host}/w{version}/w{page:1}").unwrap();
+        mutator.save("version", "v2").unwrap();
+
+        assert_eq!(missing, vec![Placeholder::new("version")]);
+        assert_eq!(
+            mutator.value(&Placeholder::new("version"), &HashMap::new()),
+            Some("v2".to_string())
+        );
+    }
+
+    #[test]
+    fn check_cycles_should_name_the_cycle() {
+        let error = check_cycles(&variables(&[
+            ("a", "w{b}"),
+            ("b", "w{c}/w{a}"),
+            ("c", "plain"),
+        ]))
+        .unwrap_err();
+
+        assert_eq!(error, "workspace variables refer to each other: a → b → a");
     }
 }
@@ -1,13 +1,16 @@
-use super::mutators::WorkspaceMutator;
+use super::mutators::{self, WorkspaceMutator};
 use crate::{common::CurldCommand, settings::traits::StoredSettings};
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::HashMap};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 pub static WORKSPACE_MODULE: &str = "workspace";
 
 pub struct WorkspacesManager<'a> {
     stored_settings: &'a RefCell<dyn StoredSettings<WorkspaceSettings>>,
     workspace_settings: WorkspaceSettings,
+
+    // Shared with workspace mutators so they stay in sync with the current workspace
+    current_variables: Rc<RefCell<HashMap<String, String>>>,
 }
 
 #[derive(Deserialize, Serialize, Default)]
@@ -21,6 +24,10 @@ pub struct Workspace {
     pub name: String,
     pub variables: HashMap<String, String>,
     pub commands: Vec<CurldCommand>,
+
+    // Variables that may only be set to one of a list of values
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub allowed: HashMap<String, Vec<String>>,
 }
 
 impl<'a> WorkspacesManager<'a> {
@@ -49,14 +56,22 @@ impl<'a> WorkspacesManager<'a> {
             .borrow_mut()
             .insert_module(WORKSPACE_MODULE, &workspace_settings);
 
-        Self {
+        let mut manager = Self {
             stored_settings,
             workspace_settings,
-        }
+            current_variables: Rc::new(RefCell::new(HashMap::new())),
+        };
+        manager.refresh_current_variables();
+
+        manager
     }
 
     pub fn change_workspace(&mut self, workspace_name: &str) {
-        if let None = self.workspace_settings.workspaces.get(workspace_name) {
+        if !self
+            .workspace_settings
+            .workspaces
+            .contains_key(workspace_name)
+        {
             let created_workspace = Workspace {
                 name: workspace_name.to_string(),
                 ..Default::default()
@@ -68,6 +83,7 @@ impl<'a> WorkspacesManager<'a> {
         }
 
         self.workspace_settings.current_workspace = workspace_name.to_string();
+        self.refresh_current_variables();
 
         self.stored_settings
             .borrow_mut()
@@ -91,13 +107,69 @@ impl<'a> WorkspacesManager<'a> {
             .collect()
     }
 
-    pub fn set_variable(&mut self, key: &str, value: &str) {
+    /// Sets a variable in the current workspace, unless it would make variables refer to
+    /// each other in a cycle
+    pub fn set_variable(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.check_variable(key, value)?;
         let mut workspace = self.get_current_workspace().clone();
 
         workspace
             .variables
             .insert(key.to_string(), value.to_string());
 
+        self.workspace_settings
+            .workspaces
+            .insert(workspace.name.clone(), workspace);
+        // Keeps values the mutators were given that aren't saved yet
+        self.current_variables
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+
+        self.stored_settings
+            .borrow_mut()
+            .insert_module(WORKSPACE_MODULE, &self.workspace_settings);
+
+        Ok(())
+    }
+
+    // Checked against the values the mutators see, which include ones not saved yet
+    fn check_variable(&self, key: &str, value: &str) -> Result<(), String> {
+        let mut variables = self.current_variables.borrow().clone();
+        variables.insert(key.to_string(), value.to_string());
+
+        mutators::check_cycles(&variables)
+    }
+
+    /// Limits a variable to a list of values, or lifts the limit when no values are given
+    pub fn set_allowed(&mut self, key: &str, values: Vec<String>) {
+        let mut workspace = self.get_current_workspace().clone();
+        match values.is_empty() {
+            true => workspace.allowed.remove(key),
+            false => workspace.allowed.insert(key.to_string(), values),
+        };
+
+        self.workspace_settings
+            .workspaces
+            .insert(workspace.name.clone(), workspace);
+        self.stored_settings
+            .borrow_mut()
+            .insert_module(WORKSPACE_MODULE, &self.workspace_settings);
+    }
+
+    pub fn get_allowed(&self, key: &str) -> Option<&Vec<String>> {
+        self.get_current_workspace().allowed.get(key)
+    }
+
+    /// Saves values given to the workspace mutators while a command ran, such as ones
+    /// prompted for because the workspace had no value
+    pub fn save_current_variables(&mut self) {
+        let variables = self.current_variables.borrow().clone();
+        if variables == self.get_current_workspace().variables {
+            return;
+        }
+
+        let mut workspace = self.get_current_workspace().clone();
+        workspace.variables = variables;
         self.workspace_settings
             .workspaces
             .insert(workspace.name.clone(), workspace);
@@ -108,18 +180,27 @@ impl<'a> WorkspacesManager<'a> {
     }
 
     pub fn get_workspace_mutator(&self) -> WorkspaceMutator {
-        WorkspaceMutator::new(self.get_current_workspace())
+        WorkspaceMutator::new(Rc::clone(&self.current_variables))
+            .allowing(self.get_current_workspace().allowed.clone())
+    }
+
+    fn refresh_current_variables(&mut self) {
+        let variables = self.get_current_workspace().variables.clone();
+        *self.current_variables.borrow_mut() = variables;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::settings::traits::MockStoredSettings;
+    use crate::{
+        settings::traits::MockStoredSettings,
+        variables::{placeholder::Placeholder, Inserter},
+    };
 
     #[test]
     fn change_workspace_should_return_workspace_even_when_it_does_not_exist() {
-        let mut stored_settings = RefCell::new(MockStoredSettings::new());
+        let stored_settings = RefCell::new(MockStoredSettings::new());
         stored_settings
             .borrow_mut()
             .expect_get_module()
@@ -129,7 +210,7 @@ mod tests {
             .expect_insert_module()
             .returning(|_, _| ());
 
-        let mut manager = WorkspacesManager::new(&mut stored_settings);
+        let mut manager = WorkspacesManager::new(&stored_settings);
         manager.change_workspace("test");
 
         let current_workspace = manager.get_current_workspace();
@@ -139,7 +220,7 @@ mod tests {
 
     #[test]
     fn new_should_create_default_workspace_when_none_exist() {
-        let mut stored_settings = RefCell::new(MockStoredSettings::new());
+        let stored_settings = RefCell::new(MockStoredSettings::new());
         stored_settings
             .borrow_mut()
             .expect_get_module()
@@ -149,8 +230,78 @@ mod tests {
             .expect_insert_module()
             .returning(|_, _| ());
 
-        let manager = WorkspacesManager::new(&mut stored_settings);
+        let manager = WorkspacesManager::new(&stored_settings);
 
         assert_eq!(manager.workspace_settings.workspaces.len(), 1);
     }
+
+    #[test]
+    fn set_variable_should_update_existing_mutators() {
+        let stored_settings = RefCell::new(MockStoredSettings::new());
+        stored_settings
+            .borrow_mut()
+            .expect_get_module()
+            .returning(|_| None);
+        stored_settings
+            .borrow_mut()
+            .expect_insert_module()
+            .returning(|_, _| ());
+
+        let mut manager = WorkspacesManager::new(&stored_settings);
+        let mutator = manager.get_workspace_mutator();
+        manager.set_variable("token", "abc").unwrap();
+
+        assert_eq!(
+            mutator.value(&Placeholder::new("token"), &HashMap::new()),
+            Some("abc".to_string())
+        );
+    }
+
+    #[test]
+    fn set_variable_should_reject_cycles() {
+        let stored_settings = RefCell::new(MockStoredSettings::new());
+        stored_settings
+            .borrow_mut()
+            .expect_get_module()
+            .returning(|_| None);
+        stored_settings
+            .borrow_mut()
+            .expect_insert_module()
+            .returning(|_, _| ());
+
+        let mut manager = WorkspacesManager::new(&stored_settings);
+        manager.set_variable("api", "w{host}/v2").unwrap();
+        let error = manager.set_variable("host", "w{api}").unwrap_err();
+
+        assert_eq!(
+            error,
+            "workspace variables refer to each other: api → host → api"
+        );
+        assert_eq!(manager.get_current_workspace().variables.get("host"), None);
+    }
+
+    #[test]
+    fn set_allowed_should_limit_and_clear_values() {
+        let stored_settings = RefCell::new(MockStoredSettings::new());
+        stored_settings
+            .borrow_mut()
+            .expect_get_module()
+            .returning(|_| None);
+        stored_settings
+            .borrow_mut()
+            .expect_insert_module()
+            .returning(|_, _| ());
+
+        let mut manager = WorkspacesManager::new(&stored_settings);
+        manager.set_allowed("env", vec!["dev".to_string(), "prod".to_string()]);
+
+        assert_eq!(
+            manager.get_allowed("env"),
+            Some(&vec!["dev".to_string(), "prod".to_string()])
+        );
+
+        manager.set_allowed("env", vec![]);
+
+        assert_eq!(manager.get_allowed("env"), None);
+    }
 }